@@ -1,16 +1,41 @@
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::instruction::Instruction;
-use anchor_lang::solana_program::{program::invoke_signed};
+use anchor_lang::solana_program::{keccak, program::invoke_signed};
 use anchor_spl::token::{self, Token, TokenAccount, Mint};
-use anchor_spl::associated_token::{AssociatedToken, Create as ATACreate};
+use anchor_spl::associated_token::{get_associated_token_address, AssociatedToken, Create as ATACreate};
 
 declare_id!("FQRP7BsLL83pktuo4yYHABntASh9xa4wo9nCpDpwydzy");
 
 const MIN_SOL_AMOUNT: u64 = 100_000_000; // 0.1 SOL in lamports
 const MAX_SOL_AMOUNT: u64 = 2_000_000_000; // 2 SOL in lamports
 const MAX_FUND_LIMIT: u64 = 20_000_000_000; // 20 SOL in lamports
-const MAX_COMMISSION_RATE: u8 = 10; // 10%
+const MAX_COMMISSION_BPS: u16 = 1_000; // 10%
 const MAX_TOKEN_CLAIM_AVAILABLE_TIME: i64 = 60 * 60 * 24; // 24 hours
+const REFUND_GRACE_PERIOD: i64 = 60 * 60 * 24; // 24 hours
+const MAX_TOTAL_FUND_DURATION: i64 = 60 * 60 * 24 * 7; // 7 days
+const MAX_FUNDING_PHASES: usize = 4;
+const DEFAULT_MAX_ACTIVE_EXPOSURE: u64 = 10_000_000_000; // 10 SOL in lamports
+const BPS_DENOMINATOR: u64 = 10_000;
+const MAX_FEE_RECIPIENTS: usize = 4;
+const PUMP_PROGRAM_ID: Pubkey = pubkey!("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P");
+const MPL_TOKEN_METADATA_ID: Pubkey = pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+const DEFAULT_BUY_SLIPPAGE_BPS: u16 = 100; // 1%
+const MAX_BUY_SLIPPAGE_BPS: u16 = 1_000; // 10%
+const PRICE_SCALE: u128 = 1_000_000_000_000; // Fixed point scale of bonding curve prices
+const MAX_TRANCHE_INTERVAL_SLOTS: u64 = 216_000; // ~24 hours
+const PUMP_FEE_RECIPIENT: Pubkey = pubkey!("68yFSZxzLWJXkxxRGydZ63C6mHx1NLEDWmwN9Lb5yySg");
+// discriminator + fee_split + min_buy_amount + max_buy_amount + authority + fund_duration + max_fund_limit + commission_bps + token_claim_available_time + max_fund_duration + soft_cap + max_active_exposure + referral_share_bps + buy_slippage_bps
+const STATE_SIZE: usize = 8 + (4 + MAX_FEE_RECIPIENTS * (32 + 2)) + 8 + 8 + 32 + 8 + 8 + 2 + 8 + 8 + 8 + 8 + 2 + 2;
+// discriminator + fee_recipient + max_buy_amount + min_buy_amount + authority + fund_duration + max_fund_limit + commission_rate + token_claim_available_time
+const LEGACY_STATE_SIZE: usize = 8 + 32 + 8 + 8 + 32 + 8 + 8 + 1 + 8;
+// discriminator + meme_id + total_funds + start_time + end_time + authority + contributor_count + mint + unclaimed_rewards + claimed_count + status + total_commission + total_referral_commission + params + original_end_time + soft_close + total_pledged + allowlist + phases + launched_at_slot + launched_at + tranche + tokens_bought + sol_spent + tranche_count + last_tranche_slot + tranches_complete + settled_excess + reference_price + unspent_lamports + settled_pledged
+const REGISTRY_SIZE: usize = 8 + 16 + 8 + 8 + 8 + 32 + 8 + 32 + 8 + 8 + 1 + 8 + 8 + (8 + 8 + 8 + 8 + 2 + 8 + 8 + 1 + 2 + 2 + 8) + 8 + (8 + 8 + 8) + 8 + (32 + 8) + (4 + MAX_FUNDING_PHASES * (8 + 8 + 8 + 8 + 8 + 1 + 8)) + 8 + 8 + (8 + 8) + 8 + 8 + 4 + 8 + 1 + 8 + 8 + 8 + 8;
+// discriminator + meme_id + total_funds + start_time + end_time + authority + contributor_count + mint + unclaimed_rewards + claimed_count
+const LEGACY_REGISTRY_SIZE: usize = 8 + 16 + 8 + 8 + 8 + 32 + 8 + 32 + 8 + 8;
+// discriminator + meme_id + contributor + amount + commission_amount + first_contribution_time + last_contribution_time + is_claimed + is_refunded + is_settled + phase_contributed + referrer + referral_amount + is_referral_paid
+const CONTRIBUTION_SIZE: usize = 8 + 16 + 32 + 8 + 8 + 8 + 8 + 1 + 1 + 1 + MAX_FUNDING_PHASES * 8 + 32 + 8 + 1;
+// discriminator + meme_id + contributor + amount + timestamp + is_claimed
+const LEGACY_CONTRIBUTION_SIZE: usize = 8 + 16 + 32 + 8 + 8 + 1;
 
 // Include the generated IDL constants
 include!(concat!(env!("OUT_DIR"), "/pump_idl.rs"));
@@ -19,14 +44,15 @@ include!(concat!(env!("OUT_DIR"), "/pump_idl.rs"));
 pub mod meme_fund {
     use super::*;
 
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize(
         ctx: Context<Initialize>,
-        fee_recipient: Pubkey, 
+        fee_split: Vec<FeeSplit>,
         initial_min_buy_amount: u64, 
         initial_max_buy_amount: u64, 
         initial_fund_duration: i64, 
         initial_max_fund_limit: u64,
-        initial_commission_rate: u8,
+        initial_commission_bps: u16,
         initial_token_claim_available_time: i64,
     ) -> Result<()> {
         // Ensure the initial fun duration is greater than 0
@@ -35,40 +61,143 @@ pub mod meme_fund {
         // Ensure the initial min buy amount is less than or equal to the initial max buy amount
         require!(initial_min_buy_amount <= initial_max_buy_amount, MemeError::InvalidBuyAmount);
 
+        // Ensure the fee split table is well formed
+        validate_fee_split(&fee_split)?;
+
         let state = &mut ctx.accounts.state;
-        state.fee_recipient = fee_recipient;
+        state.fee_split = fee_split;
         state.min_buy_amount = initial_min_buy_amount.max(MIN_SOL_AMOUNT);
         state.max_buy_amount = initial_max_buy_amount.min(MAX_SOL_AMOUNT);
         state.fund_duration = initial_fund_duration;      
         state.max_fund_limit = initial_max_fund_limit.min(MAX_FUND_LIMIT);
-        state.commission_rate = initial_commission_rate.min(MAX_COMMISSION_RATE);
+        state.commission_bps = initial_commission_bps.min(MAX_COMMISSION_BPS);
         state.token_claim_available_time = initial_token_claim_available_time.min(MAX_TOKEN_CLAIM_AVAILABLE_TIME);
         state.authority = ctx.accounts.authority.key();
+        state.max_fund_duration = MAX_TOTAL_FUND_DURATION;
+        state.soft_cap = 0;
+        state.max_active_exposure = DEFAULT_MAX_ACTIVE_EXPOSURE;
+        state.referral_share_bps = 0;
+        state.buy_slippage_bps = DEFAULT_BUY_SLIPPAGE_BPS;
        
         Ok(())
     }
 
-    // Create a new meme registry
-    pub fn create_meme_registry(ctx: Context<CreateMemeRegistry>, meme_id: [u8; 16]) -> Result<()> {        
+    // Create a new meme registry, optionally overriding the global funding parameters,
+    // scheduling the opening in the future, enabling a soft close window, an allowlist phase,
+    // ordered funding phases and tranche buying after launch
+    pub fn create_meme_registry(
+        ctx: Context<CreateMemeRegistry>,
+        meme_id: [u8; 16],
+        args: CreateRegistryArgs,
+    ) -> Result<()> {
         let registry = &mut ctx.accounts.registry;
         let clock = Clock::get().unwrap();
         let state = &ctx.accounts.state;
+        let CreateRegistryArgs { params, start_time, soft_close, allowlist, phases, tranche } = args;
+
+        // Ensure the overrides stay within the global bounds
+        let params = match params {
+            Some(params) => {
+                params.validate()?;
+                params
+            }
+            None => RegistryParams::from_state(state),
+        };
+
+        // Ensure a scheduled opening is not in the past
+        let start_time = start_time.unwrap_or(clock.unix_timestamp);
+        require!(start_time >= clock.unix_timestamp, MemeError::InvalidStartTime);
+
+        // A zeroed soft close window disables anti-sniping extensions
+        let soft_close = match soft_close {
+            Some(soft_close) => {
+                soft_close.validate()?;
+                soft_close
+            }
+            None => SoftCloseParams::default(),
+        };
         
-        registry.meme_id = meme_id.clone();
+        registry.meme_id = meme_id;
         registry.total_funds = 0;
-        registry.start_time = clock.unix_timestamp;
-        registry.end_time = clock.unix_timestamp + state.fund_duration;
+        registry.start_time = start_time;
+        registry.end_time = start_time
+            .checked_add(params.fund_duration)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        registry.original_end_time = registry.end_time;
+
+        // An empty merkle root disables the allowlist phase
+        let allowlist = match allowlist {
+            Some(allowlist) => {
+                allowlist.validate(registry.start_time, registry.end_time)?;
+                allowlist
+            }
+            None => AllowlistParams::default(),
+        };
+
+        // A zeroed tranche amount spends the whole vault in the launch buy
+        let tranche = match tranche {
+            Some(tranche) => {
+                tranche.validate()?;
+                tranche
+            }
+            None => TrancheParams::default(),
+        };
+
         registry.authority = ctx.accounts.authority.key();
         registry.contributor_count = 0;
         registry.mint = Pubkey::default();
         registry.unclaimed_rewards = 0;
         registry.claimed_count = 0;
+        registry.status = RegistryStatus::Open;
+        registry.total_commission = 0;
+        registry.total_referral_commission = 0;
+        registry.params = params;
+        registry.soft_close = soft_close;
+        registry.total_pledged = 0;
+        registry.allowlist = allowlist;
+        registry.tranche = tranche;
+        registry.tokens_bought = 0;
+        registry.sol_spent = 0;
+        registry.tranche_count = 0;
+        registry.last_tranche_slot = 0;
+        registry.tranches_complete = false;
+        registry.settled_excess = 0;
+        registry.reference_price = 0;
+        registry.unspent_lamports = 0;
+        registry.settled_pledged = 0;
+
+        // An empty list keeps a single phase governed by the registry params
+        validate_phases(&phases, registry.start_time, registry.end_time, &params, &allowlist)?;
+        registry.phases = phases
+            .into_iter()
+            .map(|phase| FundingPhase { total_funds: 0, ..phase })
+            .collect();
+
+        // Keep the fee escrow rent exempt so partial releases never leave it below the minimum
+        let escrow_rent = Rent::get()?.minimum_balance(0);
+        let escrow_top_up = escrow_rent.saturating_sub(ctx.accounts.fee_escrow.lamports());
+        if escrow_top_up > 0 {
+            anchor_lang::solana_program::program::invoke(
+                &anchor_lang::solana_program::system_instruction::transfer(
+                    &ctx.accounts.authority.key(),
+                    &ctx.accounts.fee_escrow.key(),
+                    escrow_top_up,
+                ),
+                &[
+                    ctx.accounts.authority.to_account_info(),
+                    ctx.accounts.fee_escrow.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
 
         // Emit event
         emit!(MemeRegistryCreated {
             meme_id,
             start_time: registry.start_time,
             end_time: registry.end_time,
+            params,
+            phase_count: registry.phases.len() as u8,
         });
 
         Ok(())
@@ -76,82 +205,286 @@ pub mod meme_fund {
 
 
     // Contribute to a meme vault
-    pub fn contribute(ctx: Context<Contribute>, meme_id: [u8; 16], amount: u64) -> Result<()> {
-        let state = &ctx.accounts.state;  
+    pub fn contribute(
+        ctx: Context<Contribute>,
+        meme_id: [u8; 16],
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+        allowlist_cap: u64,
+    ) -> Result<()> {
         let registry = &mut ctx.accounts.registry;
+        let params = registry.params;
         let contribution = &mut ctx.accounts.contribution;
+        let profile = &mut ctx.accounts.profile;
         let clock = Clock::get().unwrap();
 
-        // Ensure the amount is greater than or equal to the minimum allowed
-        require!(amount >= state.min_buy_amount, MemeError::BelowMinAmount);
-
-        // Ensure the amount does not exceed the maximum allowed
-        require!(amount <= state.max_buy_amount, MemeError::ExceedsMaxAmount);
-
         // Ensure the meme id is valid
         require!(registry.meme_id == meme_id, MemeError::InvalidMemeId);
 
+        // Ensure the beneficiary is a real key, a default one marks an unused contribution
+        require!(ctx.accounts.beneficiary.key() != Pubkey::default(), MemeError::InvalidBeneficiary);
+
+        // Ensure the meme registry is accepting contributions
+        require!(registry.status == RegistryStatus::Open, MemeError::InvalidRegistryStatus);
+
         let current_time = clock.unix_timestamp;
 
+        // Ensure the meme registry has opened
+        require!(current_time >= registry.start_time, MemeError::FundNotStarted);
+
         // Ensure the meme registry has not expired 
         require!(current_time < registry.end_time, MemeError::FundExpired);
 
-        // Check if the contributor has enough balance
-        require!(ctx.accounts.contributor.lamports() >= amount, MemeError::InsufficientBalance);
+        // Pick the active funding phase, if the registry defines any
+        let phase_index = if registry.phases.is_empty() {
+            None
+        } else {
+            Some(active_phase(&registry.phases, registry.end_time, current_time).ok_or(MemeError::NoActivePhase)?)
+        };
+        let phase = phase_index.map(|index| registry.phases[index]);
+
+        // Oversubscribed rounds accept pledges past the cap and scale them down at close
+        let registry_capacity = if params.oversubscription {
+            u64::MAX
+        } else {
+            params.max_fund_limit.saturating_sub(registry.total_funds)
+        };
+        require!(registry_capacity > 0, MemeError::ExceedsMaxFundLimit);
 
-        // Calculate the commission amount and contribution amount
-        let commission_amount = amount
-            .checked_mul(state.commission_rate as u64)
-            .ok_or(MemeError::ArithmeticOverflow)?
-            .checked_div(100)
+        let phase_capacity = phase.map_or(u64::MAX, |phase| phase.phase_cap.saturating_sub(phase.total_funds));
+        require!(phase_capacity > 0, MemeError::PhaseCapReached);
+
+        // Accept only the part of the amount that fits into the remaining capacity
+        let accepted_amount = fill_to_capacity(
+            amount,
+            params.commission_bps,
+            registry_capacity.min(phase_capacity),
+        )?;
+
+        // Ensure the accepted part still meets the minimum, a fill below it is rejected
+        let min_buy_amount = phase.map_or(params.min_buy_amount, |phase| phase.min_buy_amount);
+        require!(accepted_amount >= min_buy_amount, MemeError::BelowMinAmount);
+
+        // Ensure the wallet's cumulative contribution within the active phase stays within its maximum
+        let phase_contributed = match (phase_index, phase) {
+            (Some(index), Some(phase)) => {
+                let phase_contributed = contribution.phase_contributed[index]
+                    .checked_add(accepted_amount)
+                    .ok_or(MemeError::ArithmeticOverflow)?;
+                require!(phase_contributed <= phase.max_buy_amount, MemeError::ExceedsMaxAmount);
+                Some((index, phase_contributed))
+            }
+            _ => None,
+        };
+
+        // Ensure the wallet's cumulative contribution does not exceed the maximum allowed
+        let total_contributed = contribution.amount
+            .checked_add(contribution.commission_amount)
+            .and_then(|v| v.checked_add(contribution.referral_amount))
+            .and_then(|v| v.checked_add(accepted_amount))
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        require!(total_contributed <= params.max_buy_amount, MemeError::ExceedsMaxAmount);
+
+        // Ensure the wallet's open exposure across all registries stays within the protocol limit
+        let active_exposure = profile.active_exposure
+            .checked_add(accepted_amount)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        require!(
+            active_exposure <= ctx.accounts.state.max_active_exposure,
+            MemeError::ExceedsMaxExposure
+        );
+
+        // During the allowlist phase only members of the merkle tree can contribute
+        let allowlist_only = phase.is_some_and(|phase| phase.allowlist_only);
+        if allowlist_only || registry.allowlist.is_active(current_time) {
+            let leaf = allowlist_leaf(&ctx.accounts.beneficiary.key(), allowlist_cap);
+            require!(
+                verify_merkle_proof(&proof, registry.allowlist.merkle_root, leaf),
+                MemeError::NotAllowlisted
+            );
+
+            // A zero cap means only the registry wide limits apply
+            require!(
+                allowlist_cap == 0 || total_contributed <= allowlist_cap,
+                MemeError::ExceedsAllowlistCap
+            );
+        }
+
+        // Check if the payer has enough balance
+        require!(ctx.accounts.payer.lamports() >= accepted_amount, MemeError::InsufficientBalance);
+
+        // Calculate the commission amount and contribution amount on the accepted part only
+        let (commission_amount, net_contribution_amount) = split_commission(accepted_amount, params.commission_bps)?;
+
+        // A referrer and its stats account must be passed together
+        require!(
+            ctx.accounts.referrer.is_some() == ctx.accounts.referral_stats.is_some(),
+            MemeError::InvalidReferralAccounts
+        );
+
+        // Route a share of the commission to the referrer, if one is attached
+        let referrer_key = ctx.accounts.referrer.as_ref().map(|referrer| referrer.key());
+        let referral_amount = match referrer_key {
+            Some(referrer_key) => {
+                require!(
+                    referrer_key != ctx.accounts.beneficiary.key() && referrer_key != ctx.accounts.payer.key(),
+                    MemeError::SelfReferral
+                );
+
+                // The first referrer recorded on a contribution keeps it
+                require!(
+                    contribution.referrer == Pubkey::default() || contribution.referrer == referrer_key,
+                    MemeError::ReferrerMismatch
+                );
+
+                pro_rata_allocation(commission_amount, params.referral_share_bps as u64, BPS_DENOMINATOR)?
+            }
+            None => 0,
+        };
+        let protocol_commission = commission_amount
+            .checked_sub(referral_amount)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+
+        // A default contributor means the contribution account was just created
+        let is_new_contributor = contribution.contributor == Pubkey::default();
+
+        // A referrer is counted once per contribution, when it is first credited with it
+        let is_new_referral = referrer_key.is_some() && contribution.referrer == Pubkey::default();
+
+        if is_new_contributor {
+            contribution.meme_id = meme_id;
+            contribution.contributor = ctx.accounts.beneficiary.key();
+            contribution.first_contribution_time = current_time;
+            contribution.is_claimed = false;
+            contribution.is_refunded = false;
+        }
+
+        contribution.amount = contribution.amount
+            .checked_add(net_contribution_amount)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        contribution.commission_amount = contribution.commission_amount
+            .checked_add(protocol_commission)
             .ok_or(MemeError::ArithmeticOverflow)?;
+        contribution.referral_amount = contribution.referral_amount
+            .checked_add(referral_amount)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        if let Some(referrer_key) = referrer_key {
+            contribution.referrer = referrer_key;
+        }
+        contribution.last_contribution_time = current_time;
+        if let Some((index, phase_contributed)) = phase_contributed {
+            contribution.phase_contributed[index] = phase_contributed;
+        }
+
+        // A default contributor means the profile account was just created
+        if profile.contributor == Pubkey::default() {
+            profile.contributor = ctx.accounts.beneficiary.key();
+        }
 
-        let net_contribution_amount = amount
-            .checked_sub(commission_amount)
+        profile.lifetime_contributed = profile.lifetime_contributed
+            .checked_add(accepted_amount)
             .ok_or(MemeError::ArithmeticOverflow)?;
+        profile.active_exposure = active_exposure;
+        if is_new_contributor {
+            profile.registries_joined = profile.registries_joined
+                .checked_add(1)
+                .ok_or(MemeError::ArithmeticOverflow)?;
+        }
 
-        contribution.meme_id = meme_id.clone();
-        contribution.contributor = ctx.accounts.contributor.key();
-        contribution.amount = net_contribution_amount;
-        contribution.timestamp = clock.unix_timestamp;
+        if let Some(index) = phase_index {
+            let phase = &mut registry.phases[index];
+            phase.total_funds = phase.total_funds
+                .checked_add(net_contribution_amount)
+                .ok_or(MemeError::ArithmeticOverflow)?;
+        }
 
         registry.total_funds = registry.total_funds
             .checked_add(net_contribution_amount)
             .ok_or(MemeError::ArithmeticOverflow)?;
 
-        // Check if adding this contribution would exceed the max fund limit
-        require!(
-            registry.total_funds + amount <= state.max_fund_limit,
-            MemeError::ExceedsMaxFundLimit
-        );
-            
-        registry.contributor_count = registry.contributor_count
-            .checked_add(1)
-            .ok_or(MemeError::MaxContributorsReached)?;
+        registry.total_commission = registry.total_commission
+            .checked_add(protocol_commission)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        registry.total_referral_commission = registry.total_referral_commission
+            .checked_add(referral_amount)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+
+        // Close the round early once the hard cap has been reached, or what is left of it
+        // is below the minimum buy amount
+        let capped_by_registry = accepted_amount < amount && registry_capacity <= phase_capacity;
+        let is_full = !params.oversubscription && (capped_by_registry || registry.is_full()?);
+        if is_full {
+            registry.end_time = current_time;
+            registry.set_status(RegistryStatus::Closed, current_time);
+
+            emit!(MemeRegistryFull {
+                meme_id,
+                total_funds: registry.total_funds,
+                timestamp: current_time,
+            });
+        }
+
+        // Extend the deadline when the contribution lands in the soft close window
+        let deadline_extension = if is_full {
+            0
+        } else {
+            let new_end_time = registry.soft_close.extended_end_time(
+                registry.original_end_time,
+                registry.end_time,
+                current_time,
+            )?;
+            let deadline_extension = new_end_time - registry.end_time;
+            registry.end_time = new_end_time;
+            deadline_extension
+        };
+
+        // Only count unique wallets
+        if is_new_contributor {
+            registry.contributor_count = registry.contributor_count
+                .checked_add(1)
+                .ok_or(MemeError::MaxContributorsReached)?;
+        }
 
-        // Transfer commission
+        // The whole commission, including the referral share, stays in escrow and
+        // refundable until the meme is launched
         anchor_lang::solana_program::program::invoke(
             &anchor_lang::solana_program::system_instruction::transfer(
-                &ctx.accounts.contributor.key(),
-                &ctx.accounts.fee_recipient.key(),
+                &ctx.accounts.payer.key(),
+                &ctx.accounts.fee_escrow.key(),
                 commission_amount,
             ),
             &[
-                ctx.accounts.contributor.to_account_info(),
-                ctx.accounts.fee_recipient.to_account_info(),
+                ctx.accounts.payer.to_account_info(),
+                ctx.accounts.fee_escrow.to_account_info(),
                 ctx.accounts.system_program.to_account_info(),
             ],
         )?;
 
+        if let (Some(referrer), Some(referral_stats)) = (&ctx.accounts.referrer, &mut ctx.accounts.referral_stats) {
+            // A default referrer means the stats account was just created
+            if referral_stats.referrer == Pubkey::default() {
+                referral_stats.referrer = referrer.key();
+            }
+
+            referral_stats.total_referred = referral_stats.total_referred
+                .checked_add(accepted_amount)
+                .ok_or(MemeError::ArithmeticOverflow)?;
+            if is_new_referral {
+                referral_stats.referral_count = referral_stats.referral_count
+                    .checked_add(1)
+                    .ok_or(MemeError::ArithmeticOverflow)?;
+            }
+        }
+
         // Transfer net contribution
         anchor_lang::solana_program::program::invoke(
             &anchor_lang::solana_program::system_instruction::transfer(
-                &ctx.accounts.contributor.key(),
+                &ctx.accounts.payer.key(),
                 &ctx.accounts.vault.key(),
                 net_contribution_amount,
             ),
             &[
-                ctx.accounts.contributor.to_account_info(),
+                ctx.accounts.payer.to_account_info(),
                 ctx.accounts.vault.to_account_info(),
                 ctx.accounts.system_program.to_account_info(),
             ],
@@ -159,11 +492,17 @@ pub mod meme_fund {
 
         // Emit event
         emit!(ContributionMade {
-            meme_id: meme_id.clone(),
-            contributor: ctx.accounts.contributor.key(),
-            amount,
+            meme_id,
+            contributor: ctx.accounts.beneficiary.key(),
+            payer: ctx.accounts.payer.key(),
+            requested_amount: amount,
+            amount: accepted_amount,
             commission_amount,
+            referral_amount,
             net_contribution_amount,
+            deadline_extension,
+            end_time: registry.end_time,
+            phase_index: phase_index.unwrap_or(0) as u8,
             timestamp: current_time,
         });
 
@@ -171,182 +510,280 @@ pub mod meme_fund {
     }
 
     // Start Meme creation and buying process
-    pub fn start_meme(
-        ctx: Context<StartMeme>,
+    pub fn start_meme<'info>(
+        ctx: Context<'_, '_, '_, 'info, StartMeme<'info>>,
         meme_id: [u8; 16],
         name: String,
         symbol: String,
         uri: String,
-        buy_amount: u64,
-        max_sol_cost: u64,
     ) -> Result<()> {
-        let pump_program_id = ctx.accounts.pump_program.key();
-
         require!(name.len() <= 32, MemeError::NameTooLong);
         require!(symbol.len() <= 10, MemeError::SymbolTooLong);
 
+        let clock = Clock::get()?;
+        let current_time = clock.unix_timestamp;
+
+        // Ensure the funding round is closed, met its soft cap and has not been launched
+        ctx.accounts.registry.begin_launch(current_time)?;
+
         let vault_seeds: &[&[u8]] = &[
             b"vault",
             meme_id.as_ref(),
-            &[ctx.bumps.vault],
-        ];
-        // Create token instruction
-        let create_discriminator: [u8; 8] = CREATE_DISCRIMINATOR;
-
-        let mut create_data = Vec::with_capacity(create_discriminator.len() + name.len() + symbol.len() + uri.len() + 12);
-        create_data.extend_from_slice(&create_discriminator);
-        create_data.extend_from_slice(&(name.len() as u32).to_le_bytes());
-        create_data.extend_from_slice(name.as_bytes());
-        create_data.extend_from_slice(&(symbol.len() as u32).to_le_bytes());
-        create_data.extend_from_slice(symbol.as_bytes());
-        create_data.extend_from_slice(&(uri.len() as u32).to_le_bytes());
-        create_data.extend_from_slice(uri.as_bytes());
-
-        let create_accounts = vec![
-            AccountMeta::new(ctx.accounts.mint.key(), true),
-            AccountMeta::new(ctx.accounts.mint_authority.key(), false),
-            AccountMeta::new(ctx.accounts.bonding_curve.key(), false),
-            AccountMeta::new(ctx.accounts.associated_bonding_curve.key(), false),
-            AccountMeta::new_readonly(ctx.accounts.global.key(), false),
-            AccountMeta::new_readonly(ctx.accounts.mpl_token_metadata.key(), false),
-            AccountMeta::new(ctx.accounts.metadata.key(), false),
-            AccountMeta::new(ctx.accounts.vault.key(), true),
-            AccountMeta::new_readonly(ctx.accounts.system_program.key(), false),
-            AccountMeta::new_readonly(ctx.accounts.token_program.key(), false),
-            AccountMeta::new_readonly(ctx.accounts.associated_token_program.key(), false),
-            AccountMeta::new_readonly(ctx.accounts.rent.key(), false),
-            AccountMeta::new_readonly(ctx.accounts.event_authority.key(), false),
-            AccountMeta::new_readonly(ctx.accounts.pump_program.key(), false),
+            &[ctx.bumps.pump.vault],
         ];
 
-        let create_ix = Instruction {
-            program_id: pump_program_id,
-            accounts: create_accounts,
-            data: create_data,
-        };
+        pump_create(&ctx.accounts.pump.create_accounts(), &name, &symbol, &uri, &[vault_seeds])?;
+
+        // Buy the whole vault, or only the first tranche in tranche mode, at close to the
+        // price of a fresh bonding curve
+        let buy_accounts = ctx.accounts.pump.buy_accounts(ctx.accounts.authority.to_account_info());
+        let reserved_excess = ctx.accounts.registry.reserved_excess();
+        let quote = quote_pump_buy(&buy_accounts, reserved_excess, &ctx.accounts.registry.tranche)?;
+        let buy = pump_buy(
+            &buy_accounts,
+            &quote,
+            launch_price(&buy_accounts.global)?,
+            ctx.accounts.registry.params.buy_slippage_bps,
+            reserved_excess,
+            &[vault_seeds],
+        )?;
 
-        // Buy instruction
-        let buy_discriminator: [u8; 8] = BUY_DISCRIMINATOR;
-
-        let mut buy_data = Vec::with_capacity(buy_discriminator.len() + 16);
-        buy_data.extend_from_slice(&buy_discriminator);
-        buy_data.extend_from_slice(&buy_amount.to_le_bytes());
-        buy_data.extend_from_slice(&max_sol_cost.to_le_bytes());
-
-        let buy_accounts = vec![
-            AccountMeta::new_readonly(ctx.accounts.global.key(), false),
-            AccountMeta::new(ctx.accounts.fee_recipient.key(), false),
-            AccountMeta::new_readonly(ctx.accounts.mint.key(), false),
-            AccountMeta::new(ctx.accounts.bonding_curve.key(), false),
-            AccountMeta::new(ctx.accounts.associated_bonding_curve.key(), false),
-            AccountMeta::new(ctx.accounts.associated_user.key(), false),
-            AccountMeta::new(ctx.accounts.vault.key(), true),
-            AccountMeta::new_readonly(ctx.accounts.system_program.key(), false),
-            AccountMeta::new_readonly(ctx.accounts.token_program.key(), false),
-            AccountMeta::new_readonly(ctx.accounts.rent.key(), false),
-            AccountMeta::new_readonly(ctx.accounts.event_authority.key(), false),
-            AccountMeta::new_readonly(ctx.accounts.pump_program.key(), false),
+        let fee_escrow_signer_seeds: &[&[u8]] = &[
+            b"fee_escrow",
+            meme_id.as_ref(),
+            &[ctx.bumps.fee_escrow],
         ];
+        release_earned_commission(
+            &mut ctx.accounts.registry,
+            &ctx.accounts.fee_escrow.to_account_info(),
+            ctx.remaining_accounts,
+            &ctx.accounts.state.fee_split,
+            &ctx.accounts.pump.system_program.to_account_info(),
+            &[fee_escrow_signer_seeds],
+        )?;
 
-        let buy_ix = Instruction {
-            program_id: pump_program_id,
-            accounts: buy_accounts,
-            data: buy_data,
-        };
+        let registry = &mut ctx.accounts.registry;
+        registry.mint = ctx.accounts.pump.mint.key();
+        registry.record_buy(&buy, clock.slot)?;
+        registry.mark_launched(clock.slot, current_time);
 
-        // Execute instructions
-        invoke_signed(
-            &create_ix,
-            &[
-                ctx.accounts.mint.to_account_info(),
-                ctx.accounts.mint_authority.to_account_info(),
-                ctx.accounts.bonding_curve.to_account_info(),
-                ctx.accounts.associated_bonding_curve.to_account_info(),
-                ctx.accounts.global.to_account_info(),
-                ctx.accounts.mpl_token_metadata.to_account_info(),
-                ctx.accounts.metadata.to_account_info(),
-                ctx.accounts.vault.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-                ctx.accounts.token_program.to_account_info(),
-                ctx.accounts.associated_token_program.to_account_info(),
-                ctx.accounts.rent.to_account_info(),
-                ctx.accounts.event_authority.to_account_info(),
-                ctx.accounts.pump_program.to_account_info(),
-            ],
-            &[vault_seeds]
-        )?;
+        // Emit event
+        emit!(MemeStarted {
+            meme_id,
+            mint: ctx.accounts.pump.mint.key(),
+            name,
+            symbol,
+            uri,
+            total_funds: registry.total_funds, 
+            soft_cap: registry.params.soft_cap,
+            buy_amount: buy.buy_amount,
+            max_sol_cost: buy.max_sol_cost,
+            launched_at_slot: clock.slot,
+        });
 
-        // Create the associated token account using a CPI
-        msg!("Attempting to create Associated Token Account");
-        let create_ata_accounts = ATACreate {
-            payer: ctx.accounts.authority.to_account_info(),
-            associated_token: ctx.accounts.associated_user.to_account_info(),
-            authority: ctx.accounts.vault.to_account_info(),
-            mint: ctx.accounts.mint.to_account_info(),
-            system_program: ctx.accounts.system_program.to_account_info(),
-            token_program: ctx.accounts.token_program.to_account_info(),
-        };
+        Ok(())
+    }
 
-        match anchor_spl::associated_token::create_idempotent(CpiContext::new(
-            ctx.accounts.associated_token_program.to_account_info(),
-            create_ata_accounts,
-        )) {
-            Ok(_) => msg!("Associated Token Account created successfully"),
-            Err(e) => {
-                msg!("Error creating Associated Token Account: {:?}", e);
-                return Err(MemeError::ATACreationFailed.into());
-            }
-        }
+    // First step of a two-step launch: create the token and its bonding curve.
+    // The registry moves to `Launching` and the launch is finished with `launch_buy`
+    pub fn launch_create_token(
+        ctx: Context<LaunchCreateToken>,
+        meme_id: [u8; 16],
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> Result<()> {
+        require!(name.len() <= 32, MemeError::NameTooLong);
+        require!(symbol.len() <= 10, MemeError::SymbolTooLong);
 
+        let current_time = Clock::get()?.unix_timestamp;
 
-        invoke_signed(
-            &buy_ix,
-            &[
-                ctx.accounts.global.to_account_info(),
-                ctx.accounts.fee_recipient.to_account_info(),
-                ctx.accounts.mint.to_account_info(),
-                ctx.accounts.bonding_curve.to_account_info(),
-                ctx.accounts.associated_bonding_curve.to_account_info(),
-                ctx.accounts.associated_user.to_account_info(),
-                ctx.accounts.vault.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-                ctx.accounts.token_program.to_account_info(),
-                ctx.accounts.rent.to_account_info(),
-                ctx.accounts.event_authority.to_account_info(),
-                ctx.accounts.pump_program.to_account_info(),
-            ],
-            &[vault_seeds]
-        )?;
+        // Ensure the funding round is closed, met its soft cap and has not been launched
+        ctx.accounts.registry.begin_launch(current_time)?;
+
+        let vault_seeds: &[&[u8]] = &[
+            b"vault",
+            meme_id.as_ref(),
+            &[ctx.bumps.pump.vault],
+        ];
 
+        pump_create(&ctx.accounts.pump.create_accounts(), &name, &symbol, &uri, &[vault_seeds])?;
+
+        // Record the mint so the buy step can be retried on its own
         let registry = &mut ctx.accounts.registry;
-        registry.mint = ctx.accounts.mint.key();
+        registry.mint = ctx.accounts.pump.mint.key();
+        registry.set_status(RegistryStatus::Launching, current_time);
 
-        // Emit event
-        emit!(MemeStarted {
+        emit!(LaunchTokenCreated {
             meme_id,
-            mint: ctx.accounts.mint.key(),
+            mint: ctx.accounts.pump.mint.key(),
             name,
             symbol,
             uri,
-            total_funds: registry.total_funds, 
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    // Second step of a two-step launch: buy on the bonding curve with the vault's funds
+    // and release the earned commission. Can be retried until it succeeds
+    pub fn launch_buy<'info>(
+        ctx: Context<'_, '_, '_, 'info, LaunchBuy<'info>>,
+        meme_id: [u8; 16],
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.registry.status == RegistryStatus::Launching,
+            MemeError::InvalidRegistryStatus
+        );
+
+        let clock = Clock::get()?;
+        let current_time = clock.unix_timestamp;
+
+        let vault_seeds: &[&[u8]] = &[
+            b"vault",
+            meme_id.as_ref(),
+            &[ctx.bumps.pump.vault],
+        ];
+
+        // Buy the whole vault, or only the first tranche in tranche mode, at close to the
+        // price of a fresh bonding curve
+        let buy_accounts = ctx.accounts.pump.buy_accounts(ctx.accounts.authority.to_account_info());
+        let reserved_excess = ctx.accounts.registry.reserved_excess();
+        let quote = quote_pump_buy(&buy_accounts, reserved_excess, &ctx.accounts.registry.tranche)?;
+        let buy = pump_buy(
+            &buy_accounts,
+            &quote,
+            launch_price(&buy_accounts.global)?,
+            ctx.accounts.registry.params.buy_slippage_bps,
+            reserved_excess,
+            &[vault_seeds],
+        )?;
+
+        let fee_escrow_signer_seeds: &[&[u8]] = &[
+            b"fee_escrow",
+            meme_id.as_ref(),
+            &[ctx.bumps.fee_escrow],
+        ];
+        release_earned_commission(
+            &mut ctx.accounts.registry,
+            &ctx.accounts.fee_escrow.to_account_info(),
+            ctx.remaining_accounts,
+            &ctx.accounts.state.fee_split,
+            &ctx.accounts.pump.system_program.to_account_info(),
+            &[fee_escrow_signer_seeds],
+        )?;
+
+        let registry = &mut ctx.accounts.registry;
+        registry.record_buy(&buy, clock.slot)?;
+        registry.mark_launched(clock.slot, current_time);
+
+        emit!(LaunchBuyCompleted {
+            meme_id,
+            mint: registry.mint,
+            total_funds: registry.total_funds,
+            soft_cap: registry.params.soft_cap,
+            buy_amount: buy.buy_amount,
+            max_sol_cost: buy.max_sol_cost,
+            launched_at_slot: clock.slot,
         });
 
         Ok(())
     }
 
+    // Buy the next tranche of a tranche mode launch. Callable by anyone once the
+    // tranche interval has passed since the previous buy
+    pub fn execute_tranche(ctx: Context<ExecuteTranche>, meme_id: [u8; 16]) -> Result<()> {
+        let registry = &ctx.accounts.registry;
+
+        // Ensure the meme has been launched and still has tranches to buy
+        require!(registry.status == RegistryStatus::Launched, MemeError::InvalidRegistryStatus);
+        require!(!registry.tranches_complete, MemeError::TranchesComplete);
+
+        let clock = Clock::get()?;
+        let next_tranche_slot = registry.last_tranche_slot
+            .checked_add(registry.tranche.interval_slots)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        require!(clock.slot >= next_tranche_slot, MemeError::TrancheIntervalNotReached);
+
+        let vault_seeds: &[&[u8]] = &[
+            b"vault",
+            meme_id.as_ref(),
+            &[ctx.bumps.pump.vault],
+        ];
+
+        let buy_accounts = ctx.accounts.pump.buy_accounts(ctx.accounts.payer.to_account_info());
+        let reserved_excess = registry.reserved_excess();
+        let quote = quote_pump_buy(&buy_accounts, reserved_excess, &registry.tranche)?;
+
+        // Once the curve is complete or the rest of the vault buys nothing, buying is over
+        // and the remaining lamports are returned with the claims
+        if quote.curve_complete || quote.buy_amount == 0 {
+            let spendable = spendable_lamports(buy_accounts.vault.lamports(), reserved_excess)?;
+            ctx.accounts.registry.finish_tranches(spendable, clock.slot);
+            return Ok(());
+        }
+
+        // Ensure the curve has not been pushed up since the previous tranche
+        let buy = pump_buy(
+            &buy_accounts,
+            &quote,
+            registry.reference_price,
+            registry.params.buy_slippage_bps,
+            reserved_excess,
+            &[vault_seeds],
+        )?;
+
+        ctx.accounts.registry.record_buy(&buy, clock.slot)?;
+
+        Ok(())
+    }
+
+    // Stop the tranche buying of a launched meme, e.g. while the curve trades above the
+    // reference price, and return the vault's remaining lamports with the claims
+    pub fn finish_tranches(ctx: Context<FinishTranches>, _meme_id: [u8; 16]) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+
+        // Ensure the meme has been launched and still has tranches to buy
+        require!(registry.status == RegistryStatus::Launched, MemeError::InvalidRegistryStatus);
+        require!(!registry.tranches_complete, MemeError::TranchesComplete);
+
+        let spendable = spendable_lamports(ctx.accounts.vault.lamports(), registry.reserved_excess())?;
+        registry.finish_tranches(spendable, Clock::get()?.slot);
+
+        Ok(())
+    }
+
     // Claim token funds from a meme vault
     pub fn claim_tokens(ctx: Context<ClaimTokens>, _meme_id: [u8; 16]) -> Result<()> {
         let registry = &mut ctx.accounts.registry;
         let contribution = &mut ctx.accounts.contribution;
         let vault_token_account = &ctx.accounts.vault_token_account;
-        let state = &ctx.accounts.state;
 
         // Check if the meme_id matches
         require!(registry.meme_id == _meme_id, MemeError::InvalidMemeId);
 
+        // Ensure the meme has been launched and claims are not finalized
+        require!(
+            matches!(registry.status, RegistryStatus::Launched | RegistryStatus::Claiming),
+            MemeError::InvalidRegistryStatus
+        );
+
+        // Ensure every tranche has been bought so each claim gets its pro-rata share
+        require!(registry.tranches_complete, MemeError::TranchesPending);
+
         // Ensure the contribution has not been claimed
         require!(!contribution.is_claimed, MemeError::AlreadyClaimed);
 
+        // Ensure the contribution has not been refunded
+        require!(!contribution.is_refunded, MemeError::AlreadyRefunded);
+
+        // Ensure the excess of an oversubscribed round has been settled first
+        require!(
+            registry.total_pledged == 0 || contribution.is_settled,
+            MemeError::ContributionNotSettled
+        );
+
         // Check for zero amount
         require!(contribution.amount > 0, MemeError::ZeroContributionAmount);
 
@@ -356,7 +793,7 @@ pub mod meme_fund {
         let clock = Clock::get()?;
         let current_time = clock.unix_timestamp;
 
-        let claim_available_time = registry.end_time.checked_add(state.token_claim_available_time)
+        let claim_available_time = registry.end_time.checked_add(registry.params.token_claim_available_time)
         .ok_or(MemeError::ArithmeticOverflow)?;
 
         // Ensure the claim time has been reached
@@ -365,6 +802,10 @@ pub mod meme_fund {
             MemeError::ClaimTimeNotReached
         );
 
+        if registry.status == RegistryStatus::Launched {
+            registry.set_status(RegistryStatus::Claiming, current_time);
+        }
+
         let user_tokens = (contribution.amount as u128)
             .checked_mul(vault_token_account.amount as u128)
             .and_then(|v| v.checked_div(registry.total_funds as u128))
@@ -397,9 +838,46 @@ pub mod meme_fund {
             ctx.accounts.mint.decimals,
         )?;
 
+        // Return the share of the lamports left over when tranche buying ended early
+        let unspent_share = pro_rata_allocation(
+            contribution.amount,
+            registry.unspent_lamports,
+            registry.total_funds,
+        )?;
+        if unspent_share > 0 {
+            anchor_lang::solana_program::program::invoke_signed(
+                &anchor_lang::solana_program::system_instruction::transfer(
+                    &ctx.accounts.vault.key(),
+                    &ctx.accounts.contributor.key(),
+                    unspent_share,
+                ),
+                &[
+                    ctx.accounts.vault.to_account_info(),
+                    ctx.accounts.contributor.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                &[vault_seeds],
+            )?;
+
+            registry.unspent_lamports = registry.unspent_lamports
+                .checked_sub(unspent_share)
+                .ok_or(MemeError::ArithmeticOverflow)?;
+        }
+
         // Update the registry's total funds and the vault's token balance
         registry.total_funds = registry.total_funds.checked_sub(contribution.amount).ok_or(MemeError::ArithmeticOverflow)?;
 
+        // The position is closed once its tokens are claimed
+        let profile = &mut ctx.accounts.profile;
+        profile.release_exposure(
+            contribution.amount
+                .saturating_add(contribution.commission_amount)
+                .saturating_add(contribution.referral_amount)
+        );
+        profile.claims_made = profile.claims_made
+            .checked_add(1)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+
         // Set as claimed after successful transfer
         contribution.is_claimed = true;
         registry.claimed_count = registry.claimed_count
@@ -413,6 +891,8 @@ pub mod meme_fund {
             if vault_balance > 0 {
                 registry.unclaimed_rewards = vault_balance;
             }
+
+            registry.set_status(RegistryStatus::Finalized, current_time);
         }
         
         emit!(TokensClaimed {
@@ -424,62 +904,504 @@ pub mod meme_fund {
         Ok(())
     }
 
-    // Admin function to claim remaining pump rewards 
-    pub fn admin_claim_rewards(ctx: Context<AdminClaimRewards>, meme_id: [u8; 16]) -> Result<()> {
+    // Extend the deadline of an open meme registry
+    pub fn extend_registry_deadline(ctx: Context<ExtendRegistryDeadline>, meme_id: [u8; 16], new_end_time: i64) -> Result<()> {
         let registry = &mut ctx.accounts.registry;
+        let clock = Clock::get()?;
+        let current_time = clock.unix_timestamp;
 
-        // Ensure claimable rewards are available
-        require!(
-            registry.claimed_count == registry.contributor_count,
-            MemeError::NotAllTokensClaimed
-        );
+        // Ensure the meme registry is still accepting contributions
+        require!(registry.status == RegistryStatus::Open, MemeError::InvalidRegistryStatus);
+        require!(current_time < registry.end_time, MemeError::FundExpired);
 
-        if registry.unclaimed_rewards > 0 {
-            let amount = registry.unclaimed_rewards;
+        // Ensure the new deadline is later than the current one
+        let old_end_time = registry.end_time;
+        require!(new_end_time > old_end_time, MemeError::InvalidEndTime);
 
+        // Ensure the total duration stays within the configured maximum
+        let total_duration = new_end_time
+            .checked_sub(registry.start_time)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        require!(total_duration <= registry.params.max_fund_duration, MemeError::ExceedsMaxFundDuration);
+
+        registry.end_time = new_end_time;
+        registry.original_end_time = new_end_time;
+
+        // Emit event
+        emit!(RegistryDeadlineExtended {
+            meme_id,
+            old_end_time,
+            new_end_time,
+        });
+
+        Ok(())
+    }
+
+    // Close an open meme registry before its deadline
+    pub fn close_registry_early(ctx: Context<CloseRegistryEarly>, meme_id: [u8; 16]) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        let clock = Clock::get()?;
+        let current_time = clock.unix_timestamp;
+
+        // Ensure the meme registry is still accepting contributions
+        require!(registry.status == RegistryStatus::Open, MemeError::InvalidRegistryStatus);
+        require!(current_time < registry.end_time, MemeError::FundExpired);
+
+        // Ensure the round reached a configured target, either its soft cap or its hard cap,
+        // counting a round whose remaining capacity is below the minimum buy as full
+        let soft_cap_reached = registry.params.soft_cap > 0
+            && registry.total_funds >= registry.params.soft_cap;
+        let hard_cap_reached = registry.is_full()?;
+        require!(soft_cap_reached || hard_cap_reached, MemeError::FundingTargetNotReached);
+
+        let old_end_time = registry.end_time;
+        registry.end_time = current_time;
+        registry.set_status(RegistryStatus::Closed, current_time);
+
+        // Emit event
+        emit!(RegistryClosedEarly {
+            meme_id,
+            total_funds: registry.total_funds,
+            old_end_time,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    // Cancel a meme registry that has not been launched, or whose two-step launch is stuck
+    // after the token was created, and open refunds
+    pub fn cancel_meme_registry(ctx: Context<CancelMemeRegistry>, meme_id: [u8; 16]) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        let clock = Clock::get()?;
+        let current_time = clock.unix_timestamp;
+
+        // Ensure the meme has not been launched or cancelled
+        require!(
+            matches!(
+                registry.status,
+                RegistryStatus::Open | RegistryStatus::Closed | RegistryStatus::Launching
+            ),
+            MemeError::InvalidRegistryStatus
+        );
+
+        // Every pledge of an oversubscribed round is refunded in full again
+        if registry.total_pledged > 0 {
+            registry.total_funds = registry.total_pledged;
+            registry.total_pledged = 0;
+        }
+
+        registry.set_status(RegistryStatus::Cancelled, current_time);
+
+        // Emit event
+        emit!(MemeRegistryCancelled {
+            meme_id,
+            total_funds: registry.total_funds,
+            escrowed_commission: registry.total_commission.saturating_add(registry.total_referral_commission),
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    // Scale a contribution to an oversubscribed round down to its pro-rata allocation
+    // and refund the excess together with the proportional commission
+    pub fn settle_contribution(ctx: Context<SettleContribution>, meme_id: [u8; 16]) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        let contribution = &mut ctx.accounts.contribution;
+
+        // Ensure the meme has been launched
+        require!(
+            matches!(registry.status, RegistryStatus::Launched | RegistryStatus::Claiming),
+            MemeError::InvalidRegistryStatus
+        );
+
+        // Ensure the round was oversubscribed
+        require!(registry.total_pledged > 0, MemeError::NotOversubscribed);
+
+        // Ensure the contribution has not been settled or claimed
+        require!(!contribution.is_settled, MemeError::AlreadySettled);
+        require!(!contribution.is_claimed, MemeError::AlreadyClaimed);
+
+        let Settlement {
+            allocation,
+            excess_amount,
+            excess_protocol_commission,
+            excess_referral_commission,
+        } = registry.settle(
+            contribution.amount,
+            contribution.commission_amount,
+            contribution.referral_amount,
+        )?;
+        let excess_commission = excess_protocol_commission
+            .checked_add(excess_referral_commission)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+
+        if excess_amount > 0 {
             let vault_signer_seeds: &[&[u8]] = &[
                 b"vault",
                 meme_id.as_ref(),
                 &[ctx.bumps.vault],
             ];
 
-            // Transfer the remaining rewards to the fee recipient
+            // Return the excess contribution to the contributor
             anchor_lang::solana_program::program::invoke_signed(
                 &anchor_lang::solana_program::system_instruction::transfer(
                     &ctx.accounts.vault.key(),
-                    &ctx.accounts.fee_recipient.key(),
-                    amount,
+                    &ctx.accounts.contributor.key(),
+                    excess_amount,
                 ),
                 &[
                     ctx.accounts.vault.to_account_info(),
-                    ctx.accounts.fee_recipient.to_account_info(),
+                    ctx.accounts.contributor.to_account_info(),
                     ctx.accounts.system_program.to_account_info(),
                 ],
                 &[vault_signer_seeds],
             )?;
+        }
 
-            registry.unclaimed_rewards = 0;
+        if excess_commission > 0 {
+            let fee_escrow_signer_seeds: &[&[u8]] = &[
+                b"fee_escrow",
+                meme_id.as_ref(),
+                &[ctx.bumps.fee_escrow],
+            ];
+
+            // Return the commission charged on the excess
+            anchor_lang::solana_program::program::invoke_signed(
+                &anchor_lang::solana_program::system_instruction::transfer(
+                    &ctx.accounts.fee_escrow.key(),
+                    &ctx.accounts.contributor.key(),
+                    excess_commission,
+                ),
+                &[
+                    ctx.accounts.fee_escrow.to_account_info(),
+                    ctx.accounts.contributor.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                &[fee_escrow_signer_seeds],
+            )?;
+        }
+
+        contribution.amount = allocation;
+        contribution.commission_amount = contribution.commission_amount
+            .checked_sub(excess_protocol_commission)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        contribution.referral_amount = contribution.referral_amount
+            .checked_sub(excess_referral_commission)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        contribution.is_settled = true;
+
+        ctx.accounts.profile.release_exposure(excess_amount.saturating_add(excess_commission));
+
+        emit!(ContributionSettled {
+            meme_id,
+            contributor: contribution.contributor,
+            allocation,
+            refunded_amount: excess_amount,
+            refunded_commission: excess_commission,
+        });
+
+        Ok(())
+    }
+
+    // Refund a contribution from a meme vault that was never launched
+    pub fn refund_contribution(ctx: Context<RefundContribution>, meme_id: [u8; 16]) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        let contribution = &mut ctx.accounts.contribution;
+
+        let clock = Clock::get()?;
+        let current_time = clock.unix_timestamp;
+
+        // Cancel a round that missed its soft cap, or was never launched once the grace period has passed
+        if matches!(registry.status, RegistryStatus::Open | RegistryStatus::Closed) {
+            require!(
+                refund_available(
+                    registry.end_time,
+                    registry.total_funds,
+                    registry.params.soft_cap,
+                    current_time,
+                )?,
+                MemeError::RefundNotAvailable
+            );
+
+            registry.set_status(RegistryStatus::Cancelled, current_time);
+        }
+
+        // Ensure the meme has not been launched
+        require!(registry.status == RegistryStatus::Cancelled, MemeError::MemeAlreadyStarted);
+
+        // Ensure the contribution has not been refunded
+        require!(!contribution.is_refunded, MemeError::AlreadyRefunded);
+
+        // Check for zero amount
+        require!(contribution.amount > 0, MemeError::ZeroContributionAmount);
+
+        // A cancelled launch already paid for the token creation out of the vault,
+        // the remaining lamports are shared pro rata
+        let amount = refund_amount(
+            contribution.amount,
+            ctx.accounts.vault.lamports(),
+            registry.total_funds,
+        )?;
+
+        // The escrowed commission, including any referral share, is returned as well
+        let commission_amount = contribution.commission_amount
+            .checked_add(contribution.referral_amount)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+
+        // Check if the vault has enough SOL
+        require!(ctx.accounts.vault.lamports() >= amount, MemeError::InsufficientVaultBalance);
+
+        let vault_signer_seeds: &[&[u8]] = &[
+            b"vault",
+            meme_id.as_ref(),
+            &[ctx.bumps.vault],
+        ];
+
+        // Return the net contribution to the contributor
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                &ctx.accounts.vault.key(),
+                &ctx.accounts.contributor.key(),
+                amount,
+            ),
+            &[
+                ctx.accounts.vault.to_account_info(),
+                ctx.accounts.contributor.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[vault_signer_seeds],
+        )?;
+
+        if commission_amount > 0 {
+            // Check if the fee escrow has enough SOL
+            require!(
+                ctx.accounts.fee_escrow.lamports() >= commission_amount,
+                MemeError::InsufficientEscrowBalance
+            );
+
+            let fee_escrow_signer_seeds: &[&[u8]] = &[
+                b"fee_escrow",
+                meme_id.as_ref(),
+                &[ctx.bumps.fee_escrow],
+            ];
+
+            // Return the commission to the contributor
+            anchor_lang::solana_program::program::invoke_signed(
+                &anchor_lang::solana_program::system_instruction::transfer(
+                    &ctx.accounts.fee_escrow.key(),
+                    &ctx.accounts.contributor.key(),
+                    commission_amount,
+                ),
+                &[
+                    ctx.accounts.fee_escrow.to_account_info(),
+                    ctx.accounts.contributor.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                &[fee_escrow_signer_seeds],
+            )?;
 
-            Ok(())
+            registry.total_commission = registry.total_commission
+                .checked_sub(contribution.commission_amount)
+                .ok_or(MemeError::ArithmeticOverflow)?;
+            registry.total_referral_commission = registry.total_referral_commission
+                .checked_sub(contribution.referral_amount)
+                .ok_or(MemeError::ArithmeticOverflow)?;
+        }
+
+        registry.total_funds = registry.total_funds
+            .checked_sub(contribution.amount)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+
+        // Set as refunded after successful transfer
+        contribution.is_refunded = true;
+
+        // The whole position is closed
+        ctx.accounts.profile.release_exposure(
+            contribution.amount
+                .saturating_add(contribution.commission_amount)
+                .saturating_add(contribution.referral_amount)
+        );
+
+        emit!(ContributionRefunded {
+            meme_id,
+            contributor: contribution.contributor,
+            amount,
+            commission_amount,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    // Pay the escrowed referral share of a launched contribution out to its referrer
+    pub fn pay_referral(ctx: Context<PayReferral>, meme_id: [u8; 16]) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        let contribution = &mut ctx.accounts.contribution;
+        let clock = Clock::get()?;
+
+        // Ensure the meme has been launched
+        require!(
+            matches!(
+                registry.status,
+                RegistryStatus::Launched | RegistryStatus::Claiming | RegistryStatus::Finalized
+            ),
+            MemeError::InvalidRegistryStatus
+        );
+
+        // Ensure there is an unpaid referral share on the contribution
+        require!(contribution.referral_amount > 0, MemeError::NoReferralCommission);
+        require!(!contribution.is_referral_paid, MemeError::ReferralAlreadyPaid);
+
+        // Oversubscribed contributions must be scaled down before the referral share is final
+        require!(
+            registry.total_pledged == 0 || contribution.is_settled,
+            MemeError::ContributionNotSettled
+        );
+
+        let amount = contribution.referral_amount;
+
+        // Ensure the referrer is funded enough to receive a share below the rent exempt minimum
+        require_rent_exempt_after(&ctx.accounts.referrer.to_account_info(), amount)?;
+
+        let fee_escrow_signer_seeds: &[&[u8]] = &[
+            b"fee_escrow",
+            meme_id.as_ref(),
+            &[ctx.bumps.fee_escrow],
+        ];
+
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                &ctx.accounts.fee_escrow.key(),
+                &ctx.accounts.referrer.key(),
+                amount,
+            ),
+            &[
+                ctx.accounts.fee_escrow.to_account_info(),
+                ctx.accounts.referrer.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[fee_escrow_signer_seeds],
+        )?;
+
+        registry.total_referral_commission = registry.total_referral_commission
+            .checked_sub(amount)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        contribution.is_referral_paid = true;
+
+        let referral_stats = &mut ctx.accounts.referral_stats;
+        referral_stats.total_earned = referral_stats.total_earned
+            .checked_add(amount)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+
+        emit!(ReferralPaid {
+            meme_id,
+            referrer: contribution.referrer,
+            contributor: contribution.contributor,
+            amount,
+            total_earned: referral_stats.total_earned,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Admin function to claim remaining pump rewards 
+    pub fn admin_claim_rewards<'info>(
+        ctx: Context<'_, '_, '_, 'info, AdminClaimRewards<'info>>,
+        meme_id: [u8; 16],
+    ) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+
+        // Ensure claimable rewards are available. A cancelled registry only leaves the fee
+        // escrow's rent top-up to sweep, once every contribution has been refunded
+        if registry.status == RegistryStatus::Cancelled {
+            require!(registry.total_funds == 0, MemeError::NotAllContributionsRefunded);
+        } else {
+            require!(
+                registry.status == RegistryStatus::Finalized,
+                MemeError::NotAllTokensClaimed
+            );
+        }
+
+        // Sweep the fee escrow as well, including its rent top-up, but keep unpaid referral
+        // shares and, while any are owed, the escrow's rent exempt minimum
+        let escrow_reserve = if registry.total_referral_commission > 0 {
+            registry.total_referral_commission
+                .checked_add(Rent::get()?.minimum_balance(0))
+                .ok_or(MemeError::ArithmeticOverflow)?
         } else {
-            Err(MemeError::NoRewardsToClaim.into())
+            0
+        };
+        let escrow_sweep = ctx.accounts.fee_escrow.lamports().saturating_sub(escrow_reserve);
+
+        require!(
+            registry.unclaimed_rewards > 0 || escrow_sweep > 0,
+            MemeError::NoRewardsToClaim
+        );
+
+        if registry.unclaimed_rewards > 0 {
+            let amount = registry.unclaimed_rewards;
+
+            let vault_signer_seeds: &[&[u8]] = &[
+                b"vault",
+                meme_id.as_ref(),
+                &[ctx.bumps.vault],
+            ];
+
+            // Split the remaining rewards across the fee recipients
+            distribute_fees(
+                &ctx.accounts.vault.to_account_info(),
+                ctx.remaining_accounts,
+                &ctx.accounts.state.fee_split,
+                amount,
+                &ctx.accounts.system_program.to_account_info(),
+                &[vault_signer_seeds],
+            )?;
+
+            registry.unclaimed_rewards = 0;
         }
+
+        if escrow_sweep > 0 {
+            let fee_escrow_signer_seeds: &[&[u8]] = &[
+                b"fee_escrow",
+                meme_id.as_ref(),
+                &[ctx.bumps.fee_escrow],
+            ];
+
+            distribute_fees(
+                &ctx.accounts.fee_escrow.to_account_info(),
+                ctx.remaining_accounts,
+                &ctx.accounts.state.fee_split,
+                escrow_sweep,
+                &ctx.accounts.system_program.to_account_info(),
+                &[fee_escrow_signer_seeds],
+            )?;
+
+            registry.total_commission = 0;
+
+            emit!(CommissionReleased {
+                meme_id,
+                amount: escrow_sweep,
+            });
+        }
+
+        Ok(())
     }
 
-    // Update the fee recipient wallet address
-    pub fn update_fee_recipient(ctx: Context<UpdateFeeRecipient>, new_fee_recipient: Pubkey) -> Result<()> {
-        let state = &mut ctx.accounts.state;
-        
-        // Ensure the new fee recipient is not the same as the old one
-        let old_wallet = state.fee_recipient;
-        require!(old_wallet != new_fee_recipient, MemeError::SameWalletAddress);
+    // Replace the fee split table
+    pub fn update_fee_split(ctx: Context<UpdateFeeSplit>, new_fee_split: Vec<FeeSplit>) -> Result<()> {
+        // Ensure the new fee split table is well formed
+        validate_fee_split(&new_fee_split)?;
 
-        state.fee_recipient = new_fee_recipient;
+        let state = &mut ctx.accounts.state;
+        let old_split = std::mem::replace(&mut state.fee_split, new_fee_split.clone());
 
         // Emit event
-        emit!(FeeRecipientUpdated {
-            old_wallet,
-            new_wallet: new_fee_recipient,
+        emit!(FeeSplitUpdated {
+            old_split,
+            new_split: new_fee_split,
         });
 
         Ok(())
@@ -556,280 +1478,2304 @@ pub mod meme_fund {
         Ok(())
     }
 
-    // Update commission rate
-    pub fn update_commission_rate(ctx: Context<UpdateCommissionRate>, new_rate: u8) -> Result<()> {
-        require!(new_rate <= MAX_COMMISSION_RATE, MemeError::CommissionRateTooHigh);
+    // Update commission rate, in basis points
+    pub fn update_commission_rate(ctx: Context<UpdateCommissionRate>, new_commission_bps: u16) -> Result<()> {
+        require!(new_commission_bps <= MAX_COMMISSION_BPS, MemeError::CommissionRateTooHigh);
 
         let state = &mut ctx.accounts.state;
-        let old_rate = state.commission_rate;
-        state.commission_rate = new_rate;
+        let old_bps = state.commission_bps;
+        state.commission_bps = new_commission_bps;
 
         emit!(CommissionRateUpdated {
-            old_rate,
-            new_rate,
+            old_bps,
+            new_bps: new_commission_bps,
         });
 
         Ok(())
     }
 
-    // Update the max token claim available time
-    pub fn update_max_claim_available_time(ctx: Context<UpdateMaxClaimAvailableTime>, new_claim_available_time: i64) -> Result<()> {
-        require!(new_claim_available_time <= MAX_TOKEN_CLAIM_AVAILABLE_TIME, MemeError::InvalidFundDuration);
-        
-        let state = &mut ctx.accounts.state;
-        state.token_claim_available_time = new_claim_available_time;
+    // Migrate a state account created with the legacy layout (single fee recipient,
+    // percentage commission) to the current layout
+    pub fn migrate_state(ctx: Context<MigrateState>) -> Result<()> {
+        let state_info = ctx.accounts.state.to_account_info();
+
+        // Ensure the account is a legacy state account owned by this program
+        require_keys_eq!(*state_info.owner, crate::ID, MemeError::InvalidStateAccount);
+        require!(state_info.data_len() == LEGACY_STATE_SIZE, MemeError::StateAlreadyMigrated);
+
+        let legacy = {
+            let data = state_info.try_borrow_data()?;
+            require!(data[..8] == <State as anchor_lang::Discriminator>::DISCRIMINATOR, MemeError::InvalidStateAccount);
+            LegacyState::deserialize(&mut &data[8..])?
+        };
+
+        // Only the state authority can migrate
+        require_keys_eq!(legacy.authority, ctx.accounts.authority.key(), MemeError::InvalidStateAccount);
+
+        // Percentages map exactly onto basis points
+        let commission_bps = (legacy.commission_rate as u16)
+            .saturating_mul(100)
+            .min(MAX_COMMISSION_BPS);
+
+        let state = State {
+            fee_split: vec![FeeSplit {
+                wallet: legacy.fee_recipient,
+                bps: BPS_DENOMINATOR as u16,
+            }],
+            max_buy_amount: legacy.max_buy_amount,
+            min_buy_amount: legacy.min_buy_amount,
+            authority: legacy.authority,
+            fund_duration: legacy.fund_duration,
+            max_fund_limit: legacy.max_fund_limit,
+            commission_bps,
+            token_claim_available_time: legacy.token_claim_available_time,
+            max_fund_duration: MAX_TOTAL_FUND_DURATION,
+            soft_cap: 0,
+            max_active_exposure: DEFAULT_MAX_ACTIVE_EXPOSURE,
+            referral_share_bps: 0,
+            buy_slippage_bps: DEFAULT_BUY_SLIPPAGE_BPS,
+        };
+
+        // Top up rent for the larger layout
+        top_up_rent(
+            &ctx.accounts.authority.to_account_info(),
+            &state_info,
+            &ctx.accounts.system_program.to_account_info(),
+            STATE_SIZE,
+        )?;
+
+        state_info.realloc(STATE_SIZE, true)?;
+        state.try_serialize(&mut &mut state_info.try_borrow_mut_data()?[..])?;
+
+        // Emit event
+        emit!(StateMigrated {
+            authority: legacy.authority,
+            commission_bps,
+        });
 
         Ok(())
     }
 
-}
+    // Migrate a meme registry created with the legacy layout, so rounds that are live at
+    // the upgrade keep working. The status is derived from the launch and claim progress
+    pub fn migrate_registry(ctx: Context<MigrateRegistry>, meme_id: [u8; 16]) -> Result<()> {
+        let registry_info = ctx.accounts.registry.to_account_info();
 
-// States
-#[account]
-pub struct State {
-    pub fee_recipient: Pubkey,
-    pub max_buy_amount: u64,
-    pub min_buy_amount: u64,
-    pub authority: Pubkey,
-    pub fund_duration: i64,
-    pub max_fund_limit: u64,
-    pub commission_rate: u8,
-    pub token_claim_available_time: i64,
-}
+        // Ensure the account is a legacy registry owned by this program
+        require_keys_eq!(*registry_info.owner, crate::ID, MemeError::InvalidLegacyAccount);
+        require!(registry_info.data_len() == LEGACY_REGISTRY_SIZE, MemeError::AccountAlreadyMigrated);
 
-#[account]
-pub struct MemeRegistry {
-    pub meme_id: [u8; 16],
+        let legacy = {
+            let data = registry_info.try_borrow_data()?;
+            require!(data[..8] == <MemeRegistry as anchor_lang::Discriminator>::DISCRIMINATOR, MemeError::InvalidLegacyAccount);
+            LegacyMemeRegistry::deserialize(&mut &data[8..])?
+        };
+
+        // Legacy rounds were funded under the global parameters, without a minimum raise
+        let params = RegistryParams {
+            soft_cap: 0,
+            ..RegistryParams::from_state(&ctx.accounts.state)
+        };
+        let registry = legacy.migrate(params, Clock::get()?.unix_timestamp);
+
+        // Top up rent for the larger layout
+        top_up_rent(
+            &ctx.accounts.authority.to_account_info(),
+            &registry_info,
+            &ctx.accounts.system_program.to_account_info(),
+            REGISTRY_SIZE,
+        )?;
+
+        registry_info.realloc(REGISTRY_SIZE, true)?;
+        registry.try_serialize(&mut &mut registry_info.try_borrow_mut_data()?[..])?;
+
+        // Keep the fee escrow rent exempt, like the one of a new registry
+        top_up_rent(
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.fee_escrow.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            0,
+        )?;
+
+        emit!(RegistryMigrated {
+            meme_id,
+            status: registry.status,
+        });
+
+        Ok(())
+    }
+
+    // Migrate a contribution created with the legacy layout and open the contributor
+    // profile its position counts towards. Its registry has to be migrated first
+    pub fn migrate_contribution(ctx: Context<MigrateContribution>, meme_id: [u8; 16]) -> Result<()> {
+        let registry_info = ctx.accounts.registry.to_account_info();
+        require_keys_eq!(*registry_info.owner, crate::ID, MemeError::InvalidLegacyAccount);
+        require!(registry_info.data_len() == REGISTRY_SIZE, MemeError::RegistryNotMigrated);
+
+        let contribution_info = ctx.accounts.contribution.to_account_info();
+
+        // Ensure the account is a legacy contribution owned by this program
+        require_keys_eq!(*contribution_info.owner, crate::ID, MemeError::InvalidLegacyAccount);
+        require!(contribution_info.data_len() == LEGACY_CONTRIBUTION_SIZE, MemeError::AccountAlreadyMigrated);
+
+        let legacy = {
+            let data = contribution_info.try_borrow_data()?;
+            require!(data[..8] == <Contribution as anchor_lang::Discriminator>::DISCRIMINATOR, MemeError::InvalidLegacyAccount);
+            LegacyContribution::deserialize(&mut &data[8..])?
+        };
+        let contribution = legacy.migrate();
+
+        // Top up rent for the larger layout
+        top_up_rent(
+            &ctx.accounts.authority.to_account_info(),
+            &contribution_info,
+            &ctx.accounts.system_program.to_account_info(),
+            CONTRIBUTION_SIZE,
+        )?;
+
+        contribution_info.realloc(CONTRIBUTION_SIZE, true)?;
+        contribution.try_serialize(&mut &mut contribution_info.try_borrow_mut_data()?[..])?;
+
+        // An unclaimed position stays open until its tokens are claimed
+        let profile = &mut ctx.accounts.profile;
+        if profile.contributor == Pubkey::default() {
+            profile.contributor = ctx.accounts.contributor.key();
+        }
+        profile.lifetime_contributed = profile.lifetime_contributed
+            .checked_add(legacy.amount)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        profile.registries_joined = profile.registries_joined
+            .checked_add(1)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        if legacy.is_claimed {
+            profile.claims_made = profile.claims_made
+                .checked_add(1)
+                .ok_or(MemeError::ArithmeticOverflow)?;
+        } else {
+            profile.active_exposure = profile.active_exposure
+                .checked_add(legacy.amount)
+                .ok_or(MemeError::ArithmeticOverflow)?;
+        }
+
+        emit!(ContributionMigrated {
+            meme_id,
+            contributor: legacy.contributor,
+            amount: legacy.amount,
+        });
+
+        Ok(())
+    }
+
+    // Update the max token claim available time
+    pub fn update_max_claim_available_time(ctx: Context<UpdateMaxClaimAvailableTime>, new_claim_available_time: i64) -> Result<()> {
+        require!(new_claim_available_time <= MAX_TOKEN_CLAIM_AVAILABLE_TIME, MemeError::InvalidFundDuration);
+        
+        let state = &mut ctx.accounts.state;
+        state.token_claim_available_time = new_claim_available_time;
+
+        Ok(())
+    }
+
+    // Update the minimum amount a meme registry must raise to be launched
+    pub fn update_soft_cap(ctx: Context<UpdateSoftCap>, new_soft_cap: u64) -> Result<()> {
+        require!(new_soft_cap <= ctx.accounts.state.max_fund_limit, MemeError::InvalidSoftCap);
+
+        let state = &mut ctx.accounts.state;
+        let old_soft_cap = state.soft_cap;
+        state.soft_cap = new_soft_cap;
+
+        // Emit event
+        emit!(SoftCapUpdated {
+            old_soft_cap,
+            new_soft_cap,
+        });
+
+        Ok(())
+    }
+
+    // Update the maximum open exposure a single wallet can hold across all meme registries
+    pub fn update_max_active_exposure(ctx: Context<UpdateMaxActiveExposure>, new_max_active_exposure: u64) -> Result<()> {
+        require!(new_max_active_exposure > 0, MemeError::InvalidMaxExposure);
+
+        let state = &mut ctx.accounts.state;
+        let old_limit = state.max_active_exposure;
+        state.max_active_exposure = new_max_active_exposure;
+
+        // Emit event
+        emit!(MaxActiveExposureUpdated {
+            old_limit,
+            new_limit: new_max_active_exposure,
+        });
+
+        Ok(())
+    }
+
+    // Update how far above the launch price the bonding curve may trade for the launch buy, in basis points
+    pub fn update_buy_slippage(ctx: Context<UpdateBuySlippage>, new_slippage_bps: u16) -> Result<()> {
+        require!(new_slippage_bps <= MAX_BUY_SLIPPAGE_BPS, MemeError::InvalidBuySlippage);
+
+        let state = &mut ctx.accounts.state;
+        let old_slippage_bps = state.buy_slippage_bps;
+        state.buy_slippage_bps = new_slippage_bps;
+
+        // Emit event
+        emit!(BuySlippageUpdated {
+            old_slippage_bps,
+            new_slippage_bps,
+        });
+
+        Ok(())
+    }
+
+    // Update the share of the commission paid to referrers, in basis points
+    pub fn update_referral_share(ctx: Context<UpdateReferralShare>, new_share_bps: u16) -> Result<()> {
+        require!(new_share_bps as u64 <= BPS_DENOMINATOR, MemeError::InvalidReferralShare);
+
+        let state = &mut ctx.accounts.state;
+        let old_share_bps = state.referral_share_bps;
+        state.referral_share_bps = new_share_bps;
+
+        // Emit event
+        emit!(ReferralShareUpdated {
+            old_share_bps,
+            new_share_bps,
+        });
+
+        Ok(())
+    }
+
+    // Update the maximum total duration a meme registry can be extended to
+    pub fn update_max_fund_duration(ctx: Context<UpdateMaxFundDuration>, new_max_fund_duration: i64) -> Result<()> {
+        require!(new_max_fund_duration > 0, MemeError::InvalidFundDuration);
+
+        let state = &mut ctx.accounts.state;
+        let old_duration = state.max_fund_duration;
+        state.max_fund_duration = new_max_fund_duration;
+
+        // Emit event
+        emit!(MaxFundDurationUpdated {
+            old_duration,
+            new_duration: new_max_fund_duration,
+        });
+
+        Ok(())
+    }
+
+}
+
+// Split an amount into the commission and the net contribution. The commission is
+// `amount * commission_bps / 10_000` rounded up, so a fractional lamport always goes
+// to the protocol and the net contribution is never more than the exact share
+fn split_commission(amount: u64, commission_bps: u16) -> Result<(u64, u64)> {
+    let commission_amount = (amount as u128)
+        .checked_mul(commission_bps as u128)
+        .and_then(|v| v.checked_add(BPS_DENOMINATOR as u128 - 1))
+        .and_then(|v| v.checked_div(BPS_DENOMINATOR as u128))
+        .ok_or(MemeError::ArithmeticOverflow)? as u64;
+
+    let net_contribution_amount = amount
+        .checked_sub(commission_amount)
+        .ok_or(MemeError::ArithmeticOverflow)?;
+
+    Ok((commission_amount, net_contribution_amount))
+}
+
+// Largest part of the amount whose net contribution fits into the remaining capacity
+fn fill_to_capacity(amount: u64, commission_bps: u16, remaining_capacity: u64) -> Result<u64> {
+    let (_, net_contribution_amount) = split_commission(amount, commission_bps)?;
+    if net_contribution_amount <= remaining_capacity {
+        return Ok(amount);
+    }
+
+    // floor(capacity * 10_000 / (10_000 - bps)) keeps the net part at or below the capacity
+    let accepted_amount = (remaining_capacity as u128)
+        .checked_mul(BPS_DENOMINATOR as u128)
+        .and_then(|v| v.checked_div((BPS_DENOMINATOR as u128).checked_sub(commission_bps as u128)?))
+        .ok_or(MemeError::ArithmeticOverflow)?;
+
+    Ok((accepted_amount as u64).min(amount))
+}
+
+// Share of `total` proportional to `amount / basis`, rounded down
+fn pro_rata_allocation(amount: u64, total: u64, basis: u64) -> Result<u64> {
+    let allocation = (amount as u128)
+        .checked_mul(total as u128)
+        .and_then(|v| v.checked_div(basis as u128))
+        .ok_or(MemeError::ArithmeticOverflow)?;
+
+    Ok(allocation as u64)
+}
+
+// Whether a round that was not launched can be refunded: straight away once its deadline
+// passed below the soft cap, otherwise after the grace period following the deadline
+fn refund_available(end_time: i64, total_funds: u64, soft_cap: u64, current_time: i64) -> Result<bool> {
+    if current_time >= end_time && total_funds < soft_cap {
+        return Ok(true);
+    }
+
+    let refund_available_time = end_time
+        .checked_add(REFUND_GRACE_PERIOD)
+        .ok_or(MemeError::ArithmeticOverflow)?;
+
+    Ok(current_time >= refund_available_time)
+}
+
+// Refund of a contribution from the vault, scaled down pro rata when the vault holds less
+// than the outstanding contributions
+fn refund_amount(amount: u64, vault_lamports: u64, total_funds: u64) -> Result<u64> {
+    if vault_lamports >= total_funds {
+        return Ok(amount);
+    }
+
+    pro_rata_allocation(amount, vault_lamports, total_funds)
+}
+
+// Ensure the fee split table has between one and MAX_FEE_RECIPIENTS unique wallets with weights summing to 100%
+fn validate_fee_split(fee_split: &[FeeSplit]) -> Result<()> {
+    require!(
+        !fee_split.is_empty() && fee_split.len() <= MAX_FEE_RECIPIENTS,
+        MemeError::InvalidFeeSplit
+    );
+
+    let mut total_bps: u64 = 0;
+    for (index, split) in fee_split.iter().enumerate() {
+        require!(split.bps > 0, MemeError::InvalidFeeSplit);
+        require!(
+            fee_split[..index].iter().all(|other| other.wallet != split.wallet),
+            MemeError::InvalidFeeSplit
+        );
+        total_bps += split.bps as u64;
+    }
+    require!(total_bps == BPS_DENOMINATOR, MemeError::InvalidFeeSplit);
+
+    Ok(())
+}
+
+// Split an amount by the fee split weights, rounding down; the last recipient takes the remainder
+fn fee_split_amounts(amount: u64, fee_split: &[FeeSplit]) -> Result<Vec<u64>> {
+    let mut remaining = amount;
+    let mut amounts = Vec::with_capacity(fee_split.len());
+
+    for (index, split) in fee_split.iter().enumerate() {
+        let share = if index + 1 == fee_split.len() {
+            remaining
+        } else {
+            pro_rata_allocation(amount, split.bps as u64, BPS_DENOMINATOR)?
+        };
+        remaining = remaining.checked_sub(share).ok_or(MemeError::ArithmeticOverflow)?;
+        amounts.push(share);
+    }
+
+    Ok(amounts)
+}
+
+// Pay an amount out to every fee recipient. The recipients are passed as remaining accounts
+// in the same order as the fee split table and are validated against it
+fn distribute_fees<'info>(
+    from: &AccountInfo<'info>,
+    recipients: &[AccountInfo<'info>],
+    fee_split: &[FeeSplit],
+    amount: u64,
+    system_program: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    require!(recipients.len() == fee_split.len(), MemeError::InvalidFeeRecipient);
+
+    let amounts = fee_split_amounts(amount, fee_split)?;
+    for ((recipient, split), share) in recipients.iter().zip(fee_split).zip(amounts) {
+        require!(
+            recipient.key() == split.wallet && recipient.is_writable,
+            MemeError::InvalidFeeRecipient
+        );
+
+        if share == 0 {
+            continue;
+        }
+
+        require_rent_exempt_after(recipient, share)?;
+
+        invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(from.key, recipient.key, share),
+            &[from.clone(), recipient.clone(), system_program.clone()],
+            signer_seeds,
+        )?;
+    }
+
+    Ok(())
+}
+
+// Fund `account` up to the rent exempt minimum of `data_len` bytes from `payer`
+fn top_up_rent<'info>(
+    payer: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    data_len: usize,
+) -> Result<()> {
+    let required_lamports = Rent::get()?.minimum_balance(data_len);
+    let missing_lamports = required_lamports.saturating_sub(account.lamports());
+    if missing_lamports > 0 {
+        anchor_lang::solana_program::program::invoke(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                payer.key,
+                account.key,
+                missing_lamports,
+            ),
+            &[
+                payer.clone(),
+                account.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    }
+
+    Ok(())
+}
+
+// Ensure a payout leaves the recipient rent exempt, the runtime rejects a transfer
+// that would leave it funded below the minimum
+fn require_rent_exempt_after(recipient: &AccountInfo, amount: u64) -> Result<()> {
+    let rent_exempt_minimum = Rent::get()?.minimum_balance(recipient.data_len());
+    require!(
+        recipient.lamports().saturating_add(amount) >= rent_exempt_minimum,
+        MemeError::RecipientNotRentExempt
+    );
+
+    Ok(())
+}
+
+// Address of a PDA owned by the pump program
+fn pump_pda(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &PUMP_PROGRAM_ID).0
+}
+
+// Address of the Metaplex metadata account of a mint
+fn metadata_pda(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"metadata", MPL_TOKEN_METADATA_ID.as_ref(), mint.as_ref()],
+        &MPL_TOKEN_METADATA_ID,
+    ).0
+}
+
+// Deserialize an account owned by the pump program after checking its owner and discriminator
+fn load_pump_account<T: AnchorDeserialize>(
+    account: &AccountInfo,
+    discriminator: [u8; 8],
+    error: MemeError,
+) -> Result<T> {
+    if *account.owner != PUMP_PROGRAM_ID {
+        return Err(error.into());
+    }
+
+    let data = account.try_borrow_data()?;
+    if data.len() < 8 || data[..8] != discriminator {
+        return Err(error.into());
+    }
+
+    T::deserialize(&mut &data[8..]).map_err(|_| error.into())
+}
+
+// Tokens received for `sol_amount` on the bonding curve, capped at the real token reserves
+fn tokens_for_sol(curve: &PumpBondingCurve, sol_amount: u64) -> Result<u64> {
+    if sol_amount == 0 {
+        return Ok(0);
+    }
+
+    let virtual_token_reserves = curve.virtual_token_reserves as u128;
+    let virtual_sol_reserves = curve.virtual_sol_reserves as u128;
+    let product = virtual_sol_reserves
+        .checked_mul(virtual_token_reserves)
+        .ok_or(MemeError::ArithmeticOverflow)?;
+    let new_virtual_sol_reserves = virtual_sol_reserves
+        .checked_add(sol_amount as u128)
+        .ok_or(MemeError::ArithmeticOverflow)?;
+    let new_virtual_token_reserves = product
+        .checked_div(new_virtual_sol_reserves)
+        .and_then(|v| v.checked_add(1))
+        .ok_or(MemeError::ArithmeticOverflow)?;
+    let tokens = virtual_token_reserves.saturating_sub(new_virtual_token_reserves) as u64;
+
+    Ok(tokens.min(curve.real_token_reserves))
+}
+
+// Size a buy for the spendable lamports. The SOL budget leaves room for the pump fee and is
+// used as `max_sol_cost`. The curve is read in the same instruction as the buy, so no slippage
+// margin is needed: pricing the tokens one lamport below the budget absorbs the rounding up of
+// the pump program. Returns the token amount and the max SOL cost
+fn size_pump_buy(
+    spendable_lamports: u64,
+    fee_basis_points: u64,
+    curve: &PumpBondingCurve,
+) -> Result<(u64, u64)> {
+    let max_sol_cost = (spendable_lamports as u128)
+        .checked_mul(BPS_DENOMINATOR as u128)
+        .and_then(|v| v.checked_div((BPS_DENOMINATOR as u128).checked_add(fee_basis_points as u128)?))
+        .ok_or(MemeError::ArithmeticOverflow)? as u64;
+
+    Ok((tokens_for_sol(curve, max_sol_cost.saturating_sub(1))?, max_sol_cost))
+}
+
+// Spot price of a bonding curve in lamports per token base unit, scaled by PRICE_SCALE
+fn curve_price(virtual_sol_reserves: u64, virtual_token_reserves: u64) -> Result<u64> {
+    (virtual_sol_reserves as u128)
+        .checked_mul(PRICE_SCALE)
+        .and_then(|v| v.checked_div(virtual_token_reserves as u128))
+        .and_then(|v| u64::try_from(v).ok())
+        .ok_or(MemeError::ArithmeticOverflow.into())
+}
+
+// Ensure a spot price is at most `slippage_bps` above the reference price
+fn check_price(spot_price: u64, reference_price: u64, slippage_bps: u16) -> Result<()> {
+    let max_price = (reference_price as u128)
+        .checked_mul(BPS_DENOMINATOR as u128 + slippage_bps as u128)
+        .map(|v| v / BPS_DENOMINATOR as u128)
+        .ok_or(MemeError::ArithmeticOverflow)?;
+    require!(spot_price as u128 <= max_price, MemeError::PriceAboveReference);
+
+    Ok(())
+}
+
+// Price of a freshly created bonding curve, the reference of the launch buy
+fn launch_price(global: &AccountInfo) -> Result<u64> {
+    let pump_global = load_pump_account::<PumpGlobal>(
+        global,
+        GLOBAL_DISCRIMINATOR,
+        MemeError::InvalidPumpGlobal,
+    )?;
+
+    curve_price(pump_global.initial_virtual_sol_reserves, pump_global.initial_virtual_token_reserves)
+}
+
+// Accounts passed to the pump `create` instruction
+struct PumpCreateAccounts<'info> {
+    mint: AccountInfo<'info>,
+    mint_authority: AccountInfo<'info>,
+    bonding_curve: AccountInfo<'info>,
+    associated_bonding_curve: AccountInfo<'info>,
+    global: AccountInfo<'info>,
+    mpl_token_metadata: AccountInfo<'info>,
+    metadata: AccountInfo<'info>,
+    vault: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    associated_token_program: AccountInfo<'info>,
+    rent: AccountInfo<'info>,
+    event_authority: AccountInfo<'info>,
+    pump_program: AccountInfo<'info>,
+}
+
+// Accounts passed to the pump `buy` instruction, plus the payer of the vault's token account
+struct PumpBuyAccounts<'info> {
+    payer: AccountInfo<'info>,
+    global: AccountInfo<'info>,
+    fee_recipient: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    bonding_curve: AccountInfo<'info>,
+    associated_bonding_curve: AccountInfo<'info>,
+    associated_user: AccountInfo<'info>,
+    vault: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    associated_token_program: AccountInfo<'info>,
+    rent: AccountInfo<'info>,
+    event_authority: AccountInfo<'info>,
+    pump_program: AccountInfo<'info>,
+}
+
+// Create the token and its bonding curve through the pump program, with the vault as creator
+fn pump_create(
+    accounts: &PumpCreateAccounts,
+    name: &str,
+    symbol: &str,
+    uri: &str,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    // Create token instruction
+    let create_discriminator: [u8; 8] = CREATE_DISCRIMINATOR;
+
+    let mut create_data = Vec::with_capacity(create_discriminator.len() + name.len() + symbol.len() + uri.len() + 12);
+    create_data.extend_from_slice(&create_discriminator);
+    create_data.extend_from_slice(&(name.len() as u32).to_le_bytes());
+    create_data.extend_from_slice(name.as_bytes());
+    create_data.extend_from_slice(&(symbol.len() as u32).to_le_bytes());
+    create_data.extend_from_slice(symbol.as_bytes());
+    create_data.extend_from_slice(&(uri.len() as u32).to_le_bytes());
+    create_data.extend_from_slice(uri.as_bytes());
+
+    let create_accounts = vec![
+        AccountMeta::new(accounts.mint.key(), true),
+        AccountMeta::new(accounts.mint_authority.key(), false),
+        AccountMeta::new(accounts.bonding_curve.key(), false),
+        AccountMeta::new(accounts.associated_bonding_curve.key(), false),
+        AccountMeta::new_readonly(accounts.global.key(), false),
+        AccountMeta::new_readonly(accounts.mpl_token_metadata.key(), false),
+        AccountMeta::new(accounts.metadata.key(), false),
+        AccountMeta::new(accounts.vault.key(), true),
+        AccountMeta::new_readonly(accounts.system_program.key(), false),
+        AccountMeta::new_readonly(accounts.token_program.key(), false),
+        AccountMeta::new_readonly(accounts.associated_token_program.key(), false),
+        AccountMeta::new_readonly(accounts.rent.key(), false),
+        AccountMeta::new_readonly(accounts.event_authority.key(), false),
+        AccountMeta::new_readonly(accounts.pump_program.key(), false),
+    ];
+
+    let create_ix = Instruction {
+        program_id: accounts.pump_program.key(),
+        accounts: create_accounts,
+        data: create_data,
+    };
+
+    invoke_signed(
+        &create_ix,
+        &[
+            accounts.mint.clone(),
+            accounts.mint_authority.clone(),
+            accounts.bonding_curve.clone(),
+            accounts.associated_bonding_curve.clone(),
+            accounts.global.clone(),
+            accounts.mpl_token_metadata.clone(),
+            accounts.metadata.clone(),
+            accounts.vault.clone(),
+            accounts.system_program.clone(),
+            accounts.token_program.clone(),
+            accounts.associated_token_program.clone(),
+            accounts.rent.clone(),
+            accounts.event_authority.clone(),
+            accounts.pump_program.clone(),
+        ],
+        signer_seeds,
+    )?;
+
+    Ok(())
+}
+
+// Outcome of a buy on the bonding curve
+struct PumpBuy {
+    buy_amount: u64,
+    max_sol_cost: u64,
+    sol_spent: u64,
+    is_final: bool,
+    end_price: u64,
+}
+
+// Buy sized from the bonding curve and the vault, before it is placed
+struct PumpQuote {
+    buy_amount: u64,
+    max_sol_cost: u64,
+    spot_price: u64,
+    curve_complete: bool,
+    is_final: bool,
+}
+
+// Size a buy with the vault's spendable lamports, or the next tranche of them. The vault
+// stays rent exempt and `reserved_excess` lamports are held back
+fn quote_pump_buy(
+    accounts: &PumpBuyAccounts,
+    reserved_excess: u64,
+    tranche: &TrancheParams,
+) -> Result<PumpQuote> {
+    let pump_global = load_pump_account::<PumpGlobal>(
+        &accounts.global,
+        GLOBAL_DISCRIMINATOR,
+        MemeError::InvalidPumpGlobal,
+    )?;
+    let bonding_curve = load_pump_account::<PumpBondingCurve>(
+        &accounts.bonding_curve,
+        BONDING_CURVE_DISCRIMINATOR,
+        MemeError::InvalidBondingCurve,
+    )?;
+
+    let (budget_lamports, is_final) = tranche.next_tranche(
+        spendable_lamports(accounts.vault.lamports(), reserved_excess)?,
+    );
+
+    let (buy_amount, max_sol_cost) = size_pump_buy(
+        budget_lamports,
+        pump_global.fee_basis_points,
+        &bonding_curve,
+    )?;
+
+    Ok(PumpQuote {
+        buy_amount,
+        max_sol_cost,
+        spot_price: curve_price(bonding_curve.virtual_sol_reserves, bonding_curve.virtual_token_reserves)?,
+        curve_complete: bonding_curve.complete,
+        is_final,
+    })
+}
+
+// Vault lamports that can be spent on the bonding curve
+fn spendable_lamports(vault_lamports: u64, reserved_excess: u64) -> Result<u64> {
+    let reserved_lamports = Rent::get()?
+        .minimum_balance(0)
+        .checked_add(reserved_excess)
+        .ok_or(MemeError::ArithmeticOverflow)?;
+
+    Ok(vault_lamports.saturating_sub(reserved_lamports))
+}
+
+// Place a quoted buy on the bonding curve. The buy is rejected once the curve trades more
+// than `slippage_bps` above the reference price, e.g. after a buy placed in front of it
+fn pump_buy(
+    accounts: &PumpBuyAccounts,
+    quote: &PumpQuote,
+    reference_price: u64,
+    slippage_bps: u16,
+    reserved_excess: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<PumpBuy> {
+    require!(!quote.curve_complete, MemeError::BondingCurveComplete);
+    check_price(quote.spot_price, reference_price, slippage_bps)?;
+    require!(quote.buy_amount > 0, MemeError::BuyAmountTooSmall);
+
+    // Create the vault's associated token account, if an earlier attempt has not already
+    msg!("Attempting to create Associated Token Account");
+    let create_ata_accounts = ATACreate {
+        payer: accounts.payer.clone(),
+        associated_token: accounts.associated_user.clone(),
+        authority: accounts.vault.clone(),
+        mint: accounts.mint.clone(),
+        system_program: accounts.system_program.clone(),
+        token_program: accounts.token_program.clone(),
+    };
+
+    match anchor_spl::associated_token::create_idempotent(CpiContext::new(
+        accounts.associated_token_program.clone(),
+        create_ata_accounts,
+    )) {
+        Ok(_) => msg!("Associated Token Account created successfully"),
+        Err(e) => {
+            msg!("Error creating Associated Token Account: {:?}", e);
+            return Err(MemeError::ATACreationFailed.into());
+        }
+    }
+
+    let vault_lamports = accounts.vault.lamports();
+
+    // Buy instruction, sized from the bonding curve and the vault's spendable lamports
+    let buy_discriminator: [u8; 8] = BUY_DISCRIMINATOR;
+
+    let mut buy_data = Vec::with_capacity(buy_discriminator.len() + 16);
+    buy_data.extend_from_slice(&buy_discriminator);
+    buy_data.extend_from_slice(&quote.buy_amount.to_le_bytes());
+    buy_data.extend_from_slice(&quote.max_sol_cost.to_le_bytes());
+
+    let buy_accounts = vec![
+        AccountMeta::new_readonly(accounts.global.key(), false),
+        AccountMeta::new(accounts.fee_recipient.key(), false),
+        AccountMeta::new_readonly(accounts.mint.key(), false),
+        AccountMeta::new(accounts.bonding_curve.key(), false),
+        AccountMeta::new(accounts.associated_bonding_curve.key(), false),
+        AccountMeta::new(accounts.associated_user.key(), false),
+        AccountMeta::new(accounts.vault.key(), true),
+        AccountMeta::new_readonly(accounts.system_program.key(), false),
+        AccountMeta::new_readonly(accounts.token_program.key(), false),
+        AccountMeta::new_readonly(accounts.rent.key(), false),
+        AccountMeta::new_readonly(accounts.event_authority.key(), false),
+        AccountMeta::new_readonly(accounts.pump_program.key(), false),
+    ];
+
+    let buy_ix = Instruction {
+        program_id: accounts.pump_program.key(),
+        accounts: buy_accounts,
+        data: buy_data,
+    };
+
+    invoke_signed(
+        &buy_ix,
+        &[
+            accounts.global.clone(),
+            accounts.fee_recipient.clone(),
+            accounts.mint.clone(),
+            accounts.bonding_curve.clone(),
+            accounts.associated_bonding_curve.clone(),
+            accounts.associated_user.clone(),
+            accounts.vault.clone(),
+            accounts.system_program.clone(),
+            accounts.token_program.clone(),
+            accounts.rent.clone(),
+            accounts.event_authority.clone(),
+            accounts.pump_program.clone(),
+        ],
+        signer_seeds,
+    )?;
+
+    // Ensure the excess pledges of an oversubscribed round are still in the vault
+    require!(accounts.vault.lamports() >= reserved_excess, MemeError::VaultReserveSpent);
+
+    // The price after this buy is the reference of the next tranche
+    let bonding_curve = load_pump_account::<PumpBondingCurve>(
+        &accounts.bonding_curve,
+        BONDING_CURVE_DISCRIMINATOR,
+        MemeError::InvalidBondingCurve,
+    )?;
+
+    Ok(PumpBuy {
+        buy_amount: quote.buy_amount,
+        max_sol_cost: quote.max_sol_cost,
+        sol_spent: vault_lamports.saturating_sub(accounts.vault.lamports()),
+        is_final: quote.is_final,
+        end_price: curve_price(bonding_curve.virtual_sol_reserves, bonding_curve.virtual_token_reserves)?,
+    })
+}
+
+
+// Release the escrowed commission earned on the launched part of the round to the
+// fee recipients. Referral shares stay in escrow until they are paid out with `pay_referral`
+fn release_earned_commission<'info>(
+    registry: &mut MemeRegistry,
+    fee_escrow: &AccountInfo<'info>,
+    recipients: &[AccountInfo<'info>],
+    fee_split: &[FeeSplit],
+    system_program: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let earned_commission = if registry.total_pledged > 0 {
+        pro_rata_allocation(registry.total_commission, registry.total_funds, registry.total_pledged)?
+    } else {
+        registry.total_commission
+    };
+
+    if earned_commission > 0 {
+        distribute_fees(
+            fee_escrow,
+            recipients,
+            fee_split,
+            earned_commission,
+            system_program,
+            signer_seeds,
+        )?;
+
+        registry.total_commission = registry.total_commission
+            .checked_sub(earned_commission)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+
+        emit!(CommissionReleased {
+            meme_id: registry.meme_id,
+            amount: earned_commission,
+        });
+    }
+
+    Ok(())
+}
+
+// Ensure funding phases are ordered, non-overlapping and cover the end of the round
+fn validate_phases(
+    phases: &[FundingPhase],
+    start_time: i64,
+    end_time: i64,
+    params: &RegistryParams,
+    allowlist: &AllowlistParams,
+) -> Result<()> {
+    require!(phases.len() <= MAX_FUNDING_PHASES, MemeError::InvalidFundingPhase);
+
+    let mut previous_end_time = start_time;
+    for phase in phases {
+        require!(
+            phase.start_time >= previous_end_time && phase.start_time < phase.end_time,
+            MemeError::InvalidFundingPhase
+        );
+        require!(
+            phase.min_buy_amount >= MIN_SOL_AMOUNT
+                && phase.min_buy_amount <= phase.max_buy_amount
+                && phase.max_buy_amount <= params.max_buy_amount,
+            MemeError::InvalidFundingPhase
+        );
+        require!(
+            phase.phase_cap > 0 && phase.phase_cap <= params.max_fund_limit,
+            MemeError::InvalidFundingPhase
+        );
+        require!(
+            !phase.allowlist_only || allowlist.merkle_root != [0u8; 32],
+            MemeError::InvalidFundingPhase
+        );
+        previous_end_time = phase.end_time;
+    }
+
+    // The last phase runs until the registry deadline, including any extension
+    if let Some(last_phase) = phases.last() {
+        require!(last_phase.end_time == end_time, MemeError::InvalidFundingPhase);
+    }
+
+    Ok(())
+}
+
+// Index of the funding phase open at the given time
+fn active_phase(phases: &[FundingPhase], end_time: i64, current_time: i64) -> Option<usize> {
+    let last_index = phases.len().checked_sub(1)?;
+
+    phases.iter().enumerate().position(|(index, phase)| {
+        // The last phase follows the registry deadline so extensions apply to it
+        let phase_end_time = if index == last_index { end_time } else { phase.end_time };
+        current_time >= phase.start_time && current_time < phase_end_time
+    })
+}
+
+// Allowlist leaf committing to the contributor and its per-wallet cap
+fn allowlist_leaf(contributor: &Pubkey, allowlist_cap: u64) -> [u8; 32] {
+    keccak::hashv(&[contributor.as_ref(), &allowlist_cap.to_le_bytes()]).to_bytes()
+}
+
+// Verify a merkle proof where each pair of nodes is hashed in sorted order
+fn verify_merkle_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        if node <= *sibling {
+            keccak::hashv(&[&node, sibling]).to_bytes()
+        } else {
+            keccak::hashv(&[sibling, &node]).to_bytes()
+        }
+    });
+
+    computed == root
+}
+
+// States
+#[account]
+pub struct State {
+    pub fee_split: Vec<FeeSplit>,
+    pub max_buy_amount: u64,
+    pub min_buy_amount: u64,
+    pub authority: Pubkey,
+    pub fund_duration: i64,
+    pub max_fund_limit: u64,
+    pub commission_bps: u16,
+    pub token_claim_available_time: i64,
+    pub max_fund_duration: i64,
+    pub soft_cap: u64,
+    pub max_active_exposure: u64,
+    pub referral_share_bps: u16,
+    pub buy_slippage_bps: u16,
+}
+
+// State layout before fee splitting and basis point commissions, read by `migrate_state`
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyState {
+    pub fee_recipient: Pubkey,
+    pub max_buy_amount: u64,
+    pub min_buy_amount: u64,
+    pub authority: Pubkey,
+    pub fund_duration: i64,
+    pub max_fund_limit: u64,
+    pub commission_rate: u8,
+    pub token_claim_available_time: i64,
+}
+
+// Registry layout before lifecycle statuses and per-registry parameters, read by `migrate_registry`
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyMemeRegistry {
+    pub meme_id: [u8; 16],
     pub total_funds: u64,
     pub start_time: i64,
     pub end_time: i64,
-    pub authority: Pubkey,
-    pub contributor_count: u64,
-    pub mint: Pubkey,
-    pub unclaimed_rewards: u64,
-    pub claimed_count: u64,
+    pub authority: Pubkey,
+    pub contributor_count: u64,
+    pub mint: Pubkey,
+    pub unclaimed_rewards: u64,
+    pub claimed_count: u64,
+}
+
+impl LegacyMemeRegistry {
+    // Status of the legacy round: open or closed before its launch, then launched, claiming
+    // or finalized with the claims. Legacy launches bought the whole vault at once
+    fn status(&self, current_time: i64) -> RegistryStatus {
+        if self.mint == Pubkey::default() {
+            if current_time < self.end_time {
+                RegistryStatus::Open
+            } else {
+                RegistryStatus::Closed
+            }
+        } else if self.claimed_count == 0 {
+            RegistryStatus::Launched
+        } else if self.claimed_count < self.contributor_count {
+            RegistryStatus::Claiming
+        } else {
+            RegistryStatus::Finalized
+        }
+    }
+
+    fn migrate(self, params: RegistryParams, current_time: i64) -> MemeRegistry {
+        let status = self.status(current_time);
+        let launched = self.mint != Pubkey::default();
+
+        MemeRegistry {
+            meme_id: self.meme_id,
+            total_funds: self.total_funds,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            authority: self.authority,
+            contributor_count: self.contributor_count,
+            mint: self.mint,
+            unclaimed_rewards: self.unclaimed_rewards,
+            claimed_count: self.claimed_count,
+            status,
+            total_commission: 0,
+            total_referral_commission: 0,
+            params,
+            original_end_time: self.end_time,
+            soft_close: SoftCloseParams::default(),
+            total_pledged: 0,
+            allowlist: AllowlistParams::default(),
+            phases: Vec::new(),
+            launched_at_slot: 0,
+            launched_at: 0,
+            tranche: TrancheParams::default(),
+            tokens_bought: 0,
+            sol_spent: 0,
+            tranche_count: if launched { 1 } else { 0 },
+            last_tranche_slot: 0,
+            tranches_complete: launched,
+            settled_excess: 0,
+            reference_price: 0,
+            unspent_lamports: 0,
+            settled_pledged: 0,
+        }
+    }
+}
+
+// Contribution layout before escrowed commissions and referrals, read by `migrate_contribution`
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyContribution {
+    pub meme_id: [u8; 16],
+    pub contributor: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+    pub is_claimed: bool,
+}
+
+impl LegacyContribution {
+    // Legacy commissions were paid out when contributing, so nothing is held in escrow
+    fn migrate(&self) -> Contribution {
+        Contribution {
+            meme_id: self.meme_id,
+            contributor: self.contributor,
+            amount: self.amount,
+            commission_amount: 0,
+            first_contribution_time: self.timestamp,
+            last_contribution_time: self.timestamp,
+            is_claimed: self.is_claimed,
+            is_refunded: false,
+            is_settled: false,
+            phase_contributed: [0; MAX_FUNDING_PHASES],
+            referrer: Pubkey::default(),
+            referral_amount: 0,
+            is_referral_paid: false,
+        }
+    }
+}
+
+// Pump global configuration, as described in idl/pump.json
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct PumpGlobal {
+    pub initialized: bool,
+    pub authority: Pubkey,
+    pub fee_recipient: Pubkey,
+    pub initial_virtual_token_reserves: u64,
+    pub initial_virtual_sol_reserves: u64,
+    pub initial_real_token_reserves: u64,
+    pub token_total_supply: u64,
+    pub fee_basis_points: u64,
+}
+
+// Pump bonding curve state, as described in idl/pump.json
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct PumpBondingCurve {
+    pub virtual_token_reserves: u64,
+    pub virtual_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    pub token_total_supply: u64,
+    pub complete: bool,
+}
+
+// Wallet receiving a weighted share of the protocol fees
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FeeSplit {
+    pub wallet: Pubkey,
+    pub bps: u16,
+}
+
+#[account]
+pub struct MemeRegistry {
+    pub meme_id: [u8; 16],
+    pub total_funds: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub authority: Pubkey,
+    pub contributor_count: u64,
+    pub mint: Pubkey,
+    pub unclaimed_rewards: u64,
+    pub claimed_count: u64,
+    pub status: RegistryStatus,
+    pub total_commission: u64,
+    pub total_referral_commission: u64,
+    pub params: RegistryParams,
+    pub original_end_time: i64,
+    pub soft_close: SoftCloseParams,
+    pub total_pledged: u64,
+    pub allowlist: AllowlistParams,
+    pub phases: Vec<FundingPhase>,
+    pub launched_at_slot: u64,
+    pub launched_at: i64,
+    pub tranche: TrancheParams,
+    pub tokens_bought: u64,
+    pub sol_spent: u64,
+    pub tranche_count: u32,
+    pub last_tranche_slot: u64,
+    pub tranches_complete: bool,
+    pub settled_excess: u64,
+    pub reference_price: u64,
+    pub unspent_lamports: u64,
+    pub settled_pledged: u64,
+}
+
+// Split of a settled pledge into the allocation kept in the round and the excess refunded
+// together with the commission charged on it
+struct Settlement {
+    allocation: u64,
+    excess_amount: u64,
+    excess_protocol_commission: u64,
+    excess_referral_commission: u64,
+}
+
+impl MemeRegistry {
+    // Move the registry to a new lifecycle status and emit the transition
+    pub fn set_status(&mut self, new_status: RegistryStatus, timestamp: i64) {
+        let old_status = self.status;
+        self.status = new_status;
+
+        emit!(RegistryStatusChanged {
+            meme_id: self.meme_id,
+            old_status,
+            new_status,
+            timestamp,
+        });
+    }
+
+    // A round is full once its remaining capacity cannot take another buy of the minimum
+    // amount, the smallest one of its phases if it defines any
+    pub fn is_full(&self) -> Result<bool> {
+        let min_buy_amount = self.phases
+            .iter()
+            .map(|phase| phase.min_buy_amount)
+            .min()
+            .unwrap_or(self.params.min_buy_amount);
+        let remaining_capacity = self.params.max_fund_limit.saturating_sub(self.total_funds);
+        let accepted_amount = fill_to_capacity(min_buy_amount, self.params.commission_bps, remaining_capacity)?;
+
+        Ok(accepted_amount < min_buy_amount)
+    }
+
+    // Close a round whose deadline has passed and ensure it can be launched exactly once
+    pub fn check_launchable(&mut self, current_time: i64) -> Result<()> {
+        require!(
+            self.mint == Pubkey::default()
+                && !matches!(
+                    self.status,
+                    RegistryStatus::Launching
+                        | RegistryStatus::Launched
+                        | RegistryStatus::Claiming
+                        | RegistryStatus::Finalized
+                ),
+            MemeError::MemeAlreadyLaunched
+        );
+        require!(self.status != RegistryStatus::Cancelled, MemeError::RegistryCancelled);
+
+        // A round is closed once its deadline passed or its hard cap was reached
+        if self.status == RegistryStatus::Open {
+            require!(current_time >= self.end_time, MemeError::FundingRoundNotClosed);
+            self.set_status(RegistryStatus::Closed, current_time);
+        }
+
+        // Ensure the minimum raise has been met
+        require!(self.total_funds >= self.params.soft_cap, MemeError::SoftCapNotReached);
+
+        Ok(())
+    }
+
+    // Check the round can be launched and set aside the excess of an oversubscribed round
+    pub fn begin_launch(&mut self, current_time: i64) -> Result<()> {
+        self.check_launchable(current_time)?;
+
+        // Spend only the cap of an oversubscribed round, the excess is returned on settlement
+        if self.params.oversubscription && self.total_funds > self.params.max_fund_limit {
+            self.total_pledged = self.total_funds;
+            self.total_funds = self.params.max_fund_limit;
+        }
+
+        Ok(())
+    }
+
+    // Excess pledges of an oversubscribed round still held in the vault
+    pub fn reserved_excess(&self) -> u64 {
+        self.total_pledged
+            .saturating_sub(self.total_funds)
+            .saturating_sub(self.settled_excess)
+    }
+
+    // Settle a pledge of an oversubscribed round. The excess still held back is shared pro
+    // rata across the pledges not settled yet, so the last settlement takes the rounding
+    // remainder and refunds never add up to more than the reserve. Commission refunds are
+    // capped by what is left in escrow
+    fn settle(&mut self, amount: u64, commission_amount: u64, referral_amount: u64) -> Result<Settlement> {
+        let unsettled_pledged = self.total_pledged
+            .checked_sub(self.settled_pledged)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        let excess_amount = pro_rata_allocation(amount, self.reserved_excess(), unsettled_pledged)?;
+        let allocation = amount
+            .checked_sub(excess_amount)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+
+        let excess_protocol_commission = pro_rata_allocation(commission_amount, excess_amount, amount)?
+            .min(self.total_commission);
+        let excess_referral_commission = pro_rata_allocation(referral_amount, excess_amount, amount)?
+            .min(self.total_referral_commission);
+
+        self.settled_pledged = self.settled_pledged
+            .checked_add(amount)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        // The refunded excess no longer needs to be held back from tranche buys
+        self.settled_excess = self.settled_excess
+            .checked_add(excess_amount)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        self.total_commission = self.total_commission
+            .checked_sub(excess_protocol_commission)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        self.total_referral_commission = self.total_referral_commission
+            .checked_sub(excess_referral_commission)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+
+        Ok(Settlement {
+            allocation,
+            excess_amount,
+            excess_protocol_commission,
+            excess_referral_commission,
+        })
+    }
+
+    // Accumulate a buy on the bonding curve, the last tranche completes the buying
+    fn record_buy(&mut self, buy: &PumpBuy, slot: u64) -> Result<()> {
+        self.tokens_bought = self.tokens_bought
+            .checked_add(buy.buy_amount)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        self.sol_spent = self.sol_spent
+            .checked_add(buy.sol_spent)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        self.tranche_count = self.tranche_count
+            .checked_add(1)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        self.last_tranche_slot = slot;
+        self.tranches_complete = buy.is_final;
+        self.reference_price = buy.end_price;
+
+        emit!(TrancheExecuted {
+            meme_id: self.meme_id,
+            tranche_index: self.tranche_count - 1,
+            buy_amount: buy.buy_amount,
+            sol_spent: buy.sol_spent,
+            tokens_bought: self.tokens_bought,
+            total_sol_spent: self.sol_spent,
+            is_final: buy.is_final,
+            slot,
+        });
+
+        Ok(())
+    }
+
+    // Stop tranche buying early and set the vault's spendable lamports aside to be returned
+    // to the contributors with their claims
+    fn finish_tranches(&mut self, spendable_lamports: u64, slot: u64) {
+        self.tranches_complete = true;
+        self.unspent_lamports = spendable_lamports;
+
+        emit!(TranchesFinished {
+            meme_id: self.meme_id,
+            tranche_count: self.tranche_count,
+            tokens_bought: self.tokens_bought,
+            unspent_lamports: spendable_lamports,
+            slot,
+        });
+    }
+
+    // Record the completed launch buy
+    pub fn mark_launched(&mut self, slot: u64, timestamp: i64) {
+        self.launched_at_slot = slot;
+        self.launched_at = timestamp;
+        self.set_status(RegistryStatus::Launched, timestamp);
+    }
+}
+
+#[account]
+pub struct ContributorProfile {
+    pub contributor: Pubkey,
+    pub lifetime_contributed: u64,
+    pub active_exposure: u64,
+    pub registries_joined: u64,
+    pub claims_made: u64,
+}
+
+impl ContributorProfile {
+    // Remove a closed or reduced position from the open exposure
+    pub fn release_exposure(&mut self, amount: u64) {
+        self.active_exposure = self.active_exposure.saturating_sub(amount);
+    }
+}
+
+// Time-boxed funding phase with its own buy limits and cap
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FundingPhase {
+    pub start_time: i64,
+    pub end_time: i64,
+    pub min_buy_amount: u64,
+    pub max_buy_amount: u64,
+    pub phase_cap: u64,
+    pub allowlist_only: bool,
+    pub total_funds: u64,
+}
+
+// Private phase at the start of a round restricted to the members of a merkle tree
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct AllowlistParams {
+    pub merkle_root: [u8; 32],
+    pub end_time: i64,
+}
+
+impl AllowlistParams {
+    pub fn validate(&self, start_time: i64, end_time: i64) -> Result<()> {
+        require!(self.merkle_root != [0u8; 32], MemeError::InvalidAllowlist);
+        require!(
+            self.end_time > start_time && self.end_time <= end_time,
+            MemeError::InvalidAllowlist
+        );
+
+        Ok(())
+    }
+
+    pub fn is_active(&self, current_time: i64) -> bool {
+        self.merkle_root != [0u8; 32] && current_time < self.end_time
+    }
+}
+
+// Anti-sniping window: contributions in the final `window` seconds push the deadline
+// back by `extension` seconds, up to `max_extension` past the original deadline
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct SoftCloseParams {
+    pub window: i64,
+    pub extension: i64,
+    pub max_extension: i64,
+}
+
+impl SoftCloseParams {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.window > 0 && self.extension > 0 && self.max_extension >= self.extension,
+            MemeError::InvalidSoftClose
+        );
+
+        Ok(())
+    }
+
+    // Deadline after a contribution at `current_time`: inside the window it is pushed back,
+    // but never past `max_extension` after the original deadline
+    pub fn extended_end_time(&self, original_end_time: i64, end_time: i64, current_time: i64) -> Result<i64> {
+        if self.window == 0 {
+            return Ok(end_time);
+        }
+
+        let time_left = end_time
+            .checked_sub(current_time)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        if time_left > self.window {
+            return Ok(end_time);
+        }
+
+        let max_end_time = original_end_time
+            .checked_add(self.max_extension)
+            .ok_or(MemeError::ArithmeticOverflow)?;
+        let new_end_time = end_time
+            .checked_add(self.extension)
+            .ok_or(MemeError::ArithmeticOverflow)?
+            .min(max_end_time);
+
+        Ok(new_end_time.max(end_time))
+    }
+}
+
+// Tranche (DCA) launch: the launch buy spends at most `tranche_amount` lamports and the rest
+// of the vault is bought by `execute_tranche` in chunks of the same size, `interval_slots` apart
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TrancheParams {
+    pub tranche_amount: u64,
+    pub interval_slots: u64,
+}
+
+impl TrancheParams {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.tranche_amount >= MIN_SOL_AMOUNT
+                && self.interval_slots > 0
+                && self.interval_slots <= MAX_TRANCHE_INTERVAL_SLOTS,
+            MemeError::InvalidTrancheParams
+        );
+
+        Ok(())
+    }
+
+    // Lamports to spend on the next buy and whether it is the last one. A remainder too
+    // small for a tranche of its own is folded into the last tranche
+    pub fn next_tranche(&self, spendable_lamports: u64) -> (u64, bool) {
+        if self.tranche_amount == 0 || spendable_lamports.saturating_sub(self.tranche_amount) < MIN_SOL_AMOUNT {
+            (spendable_lamports, true)
+        } else {
+            (self.tranche_amount, false)
+        }
+    }
+}
+
+// Optional settings of a new registry, each left out falls back to its default
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct CreateRegistryArgs {
+    pub params: Option<RegistryParams>,
+    pub start_time: Option<i64>,
+    pub soft_close: Option<SoftCloseParams>,
+    pub allowlist: Option<AllowlistParams>,
+    pub phases: Vec<FundingPhase>,
+    pub tranche: Option<TrancheParams>,
+}
+
+// Funding parameters of a single registry, either overridden at creation or snapshotted
+// from the state so later admin changes do not apply retroactively
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RegistryParams {
+    pub fund_duration: i64,
+    pub min_buy_amount: u64,
+    pub max_buy_amount: u64,
+    pub max_fund_limit: u64,
+    pub commission_bps: u16,
+    pub token_claim_available_time: i64,
+    pub soft_cap: u64,
+    pub oversubscription: bool,
+    pub referral_share_bps: u16,
+    pub buy_slippage_bps: u16,
+    pub max_fund_duration: i64,
+}
+
+impl RegistryParams {
+    pub fn from_state(state: &State) -> Self {
+        Self {
+            fund_duration: state.fund_duration,
+            min_buy_amount: state.min_buy_amount,
+            max_buy_amount: state.max_buy_amount,
+            max_fund_limit: state.max_fund_limit,
+            commission_bps: state.commission_bps,
+            token_claim_available_time: state.token_claim_available_time,
+            soft_cap: state.soft_cap,
+            oversubscription: false,
+            referral_share_bps: state.referral_share_bps,
+            buy_slippage_bps: state.buy_slippage_bps,
+            max_fund_duration: state.max_fund_duration,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        require!(self.fund_duration > 0, MemeError::InvalidFundDuration);
+        require!(self.min_buy_amount >= MIN_SOL_AMOUNT, MemeError::BelowMinAllowedAmount);
+        require!(self.max_buy_amount <= MAX_SOL_AMOUNT, MemeError::ExceedsMaxAllowedAmount);
+        require!(self.min_buy_amount <= self.max_buy_amount, MemeError::InvalidBuyAmount);
+        require!(
+            self.max_fund_limit > 0 && self.max_fund_limit <= MAX_FUND_LIMIT,
+            MemeError::InvalidMaxFundLimit
+        );
+        require!(self.commission_bps <= MAX_COMMISSION_BPS, MemeError::CommissionRateTooHigh);
+        require!(
+            self.token_claim_available_time >= 0
+                && self.token_claim_available_time <= MAX_TOKEN_CLAIM_AVAILABLE_TIME,
+            MemeError::InvalidClaimAvailableTime
+        );
+        require!(self.soft_cap <= self.max_fund_limit, MemeError::InvalidSoftCap);
+        require!(self.referral_share_bps as u64 <= BPS_DENOMINATOR, MemeError::InvalidReferralShare);
+        require!(self.buy_slippage_bps <= MAX_BUY_SLIPPAGE_BPS, MemeError::InvalidBuySlippage);
+        require!(
+            self.fund_duration <= self.max_fund_duration
+                && self.max_fund_duration <= MAX_TOTAL_FUND_DURATION,
+            MemeError::ExceedsMaxFundDuration
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RegistryStatus {
+    Open,
+    Closed,
+    Launched,
+    Claiming,
+    Finalized,
+    Cancelled,
+    // Token created by `launch_create_token`, waiting for `launch_buy`
+    Launching,
+}
+
+#[account]
+pub struct Contribution {
+    pub meme_id: [u8; 16],
+    pub contributor: Pubkey,
+    pub amount: u64,
+    pub commission_amount: u64,
+    pub first_contribution_time: i64,
+    pub last_contribution_time: i64,
+    pub is_claimed: bool,
+    pub is_refunded: bool,
+    pub is_settled: bool,
+    pub phase_contributed: [u64; MAX_FUNDING_PHASES],
+    pub referrer: Pubkey,
+    pub referral_amount: u64,
+    pub is_referral_paid: bool,
+}
+
+#[account]
+pub struct ReferralStats {
+    pub referrer: Pubkey,
+    pub total_referred: u64,
+    pub total_earned: u64,
+    pub referral_count: u64,
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = STATE_SIZE,
+        seeds = [b"state"],
+        bump
+    )]
+    pub state: Account<'info, State>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(meme_id: [u8; 16])]
+pub struct CreateMemeRegistry<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = REGISTRY_SIZE,
+        seeds = [b"registry", meme_id.as_ref()],
+        bump
+    )]
+    pub registry: Account<'info, MemeRegistry>,
+    /// CHECK: This account is only used as a PDA for receiving SOL
+    #[account(
+        seeds = [b"vault", meme_id.as_ref()],
+        bump,
+    )]
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: This account is a PDA, used as commission escrow
+    #[account(
+        mut,
+        seeds = [b"fee_escrow", meme_id.as_ref()],
+        bump,
+    )]
+    pub fee_escrow: UncheckedAccount<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump,
+        has_one = authority
+    )]
+    pub state: Account<'info, State>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(meme_id: [u8; 16])]
+pub struct Contribute<'info> {
+    /// CHECK: This account is only used as a PDA for receiving SOL
+    #[account(
+        mut,
+        seeds = [b"vault", meme_id.as_ref()],
+        bump,
+    )]
+    pub vault: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"registry", meme_id.as_ref()],
+        bump,
+    )]
+    pub registry: Account<'info, MemeRegistry>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = CONTRIBUTION_SIZE,
+        seeds = [b"contribution", meme_id.as_ref(), beneficiary.key().as_ref()],
+        bump
+    )]
+    pub contribution: Account<'info, Contribution>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + 32 + 8 + 8 + 8 + 8, // discriminator + contributor + lifetime_contributed + active_exposure + registries_joined + claims_made
+        seeds = [b"profile", beneficiary.key().as_ref()],
+        bump
+    )]
+    pub profile: Account<'info, ContributorProfile>,
+    // Funds the contribution; may differ from the beneficiary when a custodian contributes for a user
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: Only used as the key owning the contribution; the beneficiary alone can later claim or refund
+    pub beneficiary: UncheckedAccount<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump
+    )]
+    pub state: Account<'info, State>,
+    /// CHECK: This account is a PDA, used as commission escrow
+    #[account(
+        mut,
+        seeds = [b"fee_escrow", meme_id.as_ref()],
+        bump,
+    )]
+    pub fee_escrow: UncheckedAccount<'info>,
+    /// CHECK: Optional wallet that referred the contributor and earns a share of the commission
+    pub referrer: Option<UncheckedAccount<'info>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + 32 + 8 + 8 + 8, // discriminator + referrer + total_referred + total_earned + referral_count
+        seeds = [b"referral", referrer.as_ref().map(|referrer| referrer.key()).unwrap_or_default().as_ref()],
+        bump
+    )]
+    pub referral_stats: Option<Account<'info, ReferralStats>>,
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts of the pump bonding curve of a meme, shared by the launch and tranche instructions
+#[derive(Accounts)]
+#[instruction(meme_id: [u8; 16])]
+pub struct PumpAccounts<'info> {
+    /// CHECK: This account is used as a PDA for receiving and sending SOL
+    #[account(
+        mut,
+        seeds = [b"vault", meme_id.as_ref()],
+        bump
+    )]
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: This is safe because the instructions check it signs the token creation or matches the registry mint
+    #[account(mut)]
+    pub mint: UncheckedAccount<'info>,
+    /// CHECK: This is safe because we're checking the address against the pump mint authority PDA
+    #[account(
+        mut,
+        constraint = mint_authority.key() == pump_pda(&[b"mint-authority"]) @ MemeError::InvalidMintAuthority
+    )]
+    pub mint_authority: UncheckedAccount<'info>,
+    /// CHECK: This is safe because we're checking the address against the pump bonding curve PDA of the mint
+    #[account(
+        mut,
+        constraint = bonding_curve.key() == pump_pda(&[b"bonding-curve", mint.key().as_ref()]) @ MemeError::InvalidBondingCurve
+    )]
+    pub bonding_curve: UncheckedAccount<'info>,
+    /// CHECK: This is safe because we're checking the address against the bonding curve's associated token account
+    #[account(
+        mut,
+        constraint = associated_bonding_curve.key()
+            == get_associated_token_address(&bonding_curve.key(), &mint.key()) @ MemeError::InvalidAssociatedBondingCurve
+    )]
+    pub associated_bonding_curve: UncheckedAccount<'info>,
+    /// CHECK: This is safe because we're checking the address against the pump global PDA
+    #[account(
+        constraint = global.key() == pump_pda(&[b"global"]) @ MemeError::InvalidPumpGlobal
+    )]
+    pub global: UncheckedAccount<'info>,
+    /// CHECK: This is safe because we're checking the address against the Metaplex token metadata program id
+    #[account(
+        address = MPL_TOKEN_METADATA_ID @ MemeError::InvalidMetadataProgram
+    )]
+    pub mpl_token_metadata: UncheckedAccount<'info>,
+    /// CHECK: This is safe because we're checking the address against the Metaplex metadata PDA of the mint
+    #[account(
+        mut,
+        constraint = metadata.key() == metadata_pda(&mint.key()) @ MemeError::InvalidMetadata
+    )]
+    pub metadata: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+    /// CHECK: This is safe because we're checking the address against the pump event authority PDA
+    #[account(
+        constraint = event_authority.key() == pump_pda(&[b"__event_authority"]) @ MemeError::InvalidEventAuthority
+    )]
+    pub event_authority: UncheckedAccount<'info>,
+    /// CHECK: This is safe because we're checking the address against the pump program id
+    #[account(
+        address = PUMP_PROGRAM_ID @ MemeError::InvalidPumpProgram
+    )]
+    pub pump_program: UncheckedAccount<'info>,
+    /// CHECK: This is safe because we're checking the address against the pump fee recipient
+    #[account(
+        mut,
+        address = PUMP_FEE_RECIPIENT @ MemeError::InvalidPumpFeeRecipient
+    )]
+    pub fee_recipient: UncheckedAccount<'info>,
+    /// CHECK: This is safe because we're checking the address against the vault's associated token account
+    #[account(
+        mut,
+        constraint = associated_user.key()
+            == get_associated_token_address(&vault.key(), &mint.key()) @ MemeError::InvalidAssociatedUser
+    )]
+    pub associated_user: UncheckedAccount<'info>,
+}
+
+impl<'info> PumpAccounts<'info> {
+    fn create_accounts(&self) -> PumpCreateAccounts<'info> {
+        PumpCreateAccounts {
+            mint: self.mint.to_account_info(),
+            mint_authority: self.mint_authority.to_account_info(),
+            bonding_curve: self.bonding_curve.to_account_info(),
+            associated_bonding_curve: self.associated_bonding_curve.to_account_info(),
+            global: self.global.to_account_info(),
+            mpl_token_metadata: self.mpl_token_metadata.to_account_info(),
+            metadata: self.metadata.to_account_info(),
+            vault: self.vault.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            associated_token_program: self.associated_token_program.to_account_info(),
+            rent: self.rent.to_account_info(),
+            event_authority: self.event_authority.to_account_info(),
+            pump_program: self.pump_program.to_account_info(),
+        }
+    }
+
+    fn buy_accounts(&self, payer: AccountInfo<'info>) -> PumpBuyAccounts<'info> {
+        PumpBuyAccounts {
+            payer,
+            global: self.global.to_account_info(),
+            fee_recipient: self.fee_recipient.to_account_info(),
+            mint: self.mint.to_account_info(),
+            bonding_curve: self.bonding_curve.to_account_info(),
+            associated_bonding_curve: self.associated_bonding_curve.to_account_info(),
+            associated_user: self.associated_user.to_account_info(),
+            vault: self.vault.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            associated_token_program: self.associated_token_program.to_account_info(),
+            rent: self.rent.to_account_info(),
+            event_authority: self.event_authority.to_account_info(),
+            pump_program: self.pump_program.to_account_info(),
+        }
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(meme_id: [u8; 16])]
+pub struct StartMeme<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry", meme_id.as_ref()],
+        bump,
+    )]
+    pub registry: Account<'info, MemeRegistry>,
+    #[account(constraint = pump.mint.is_signer @ MemeError::InvalidMint)]
+    pub pump: PumpAccounts<'info>,
+    #[account(
+        mut,
+        constraint = authority.key() == registry.authority
+    )]
+    pub authority: Signer<'info>,
+    /// CHECK: This account is a PDA, used as commission escrow
+    #[account(
+        mut,
+        seeds = [b"fee_escrow", registry.meme_id.as_ref()],
+        bump
+    )]
+    pub fee_escrow: UncheckedAccount<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump
+    )]
+    pub state: Account<'info, State>,
 }
 
-#[account]
-pub struct Contribution {
-    pub meme_id: [u8; 16],
-    pub contributor: Pubkey,
-    pub amount: u64,
-    pub timestamp: i64,
-    pub is_claimed: bool,
+#[derive(Accounts)]
+#[instruction(meme_id: [u8; 16])]
+pub struct LaunchCreateToken<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry", meme_id.as_ref()],
+        bump,
+    )]
+    pub registry: Account<'info, MemeRegistry>,
+    #[account(constraint = pump.mint.is_signer @ MemeError::InvalidMint)]
+    pub pump: PumpAccounts<'info>,
+    #[account(
+        mut,
+        constraint = authority.key() == registry.authority
+    )]
+    pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct Initialize<'info> {
+#[instruction(meme_id: [u8; 16])]
+pub struct LaunchBuy<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry", meme_id.as_ref()],
+        bump,
+    )]
+    pub registry: Account<'info, MemeRegistry>,
+    #[account(constraint = pump.mint.key() == registry.mint @ MemeError::InvalidMint)]
+    pub pump: PumpAccounts<'info>,
+    #[account(
+        mut,
+        constraint = authority.key() == registry.authority
+    )]
+    pub authority: Signer<'info>,
+    /// CHECK: This account is a PDA, used as commission escrow
+    #[account(
+        mut,
+        seeds = [b"fee_escrow", registry.meme_id.as_ref()],
+        bump
+    )]
+    pub fee_escrow: UncheckedAccount<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump
+    )]
+    pub state: Account<'info, State>,
+}
+
+#[derive(Accounts)]
+#[instruction(meme_id: [u8; 16])]
+pub struct ExecuteTranche<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry", meme_id.as_ref()],
+        bump,
+    )]
+    pub registry: Account<'info, MemeRegistry>,
+    #[account(constraint = pump.mint.key() == registry.mint @ MemeError::InvalidMint)]
+    pub pump: PumpAccounts<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
     #[account(
-        init,
-        payer = authority,
-        space = 8 + 32 + 8 + 8 + 32 + 8 + 8 + 1 + 8, // discriminator + fee_recipient + min_buy_amount + max_buy_amount + authority + fund_duration + max_fund_limit + commission_rate + token_claim_available_time
         seeds = [b"state"],
         bump
     )]
-    pub state: Account<'info, State>,
+    pub state: Account<'info, State>,
+}
+
+#[derive(Accounts)]
+#[instruction(meme_id: [u8; 16])]
+pub struct FinishTranches<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry", meme_id.as_ref()],
+        bump,
+    )]
+    pub registry: Account<'info, MemeRegistry>,
+    /// CHECK: This account is a PDA, used as vault
+    #[account(
+        seeds = [b"vault", meme_id.as_ref()],
+        bump
+    )]
+    pub vault: UncheckedAccount<'info>,
+    #[account(seeds = [b"state"], bump, has_one = authority)]
+    pub state: Account<'info, State>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(meme_id: [u8; 16])]
+pub struct ClaimTokens<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry", meme_id.as_ref()],
+        bump,
+    )]
+    pub registry: Account<'info, MemeRegistry>,
+    #[account(
+        mut,
+        seeds = [b"contribution", meme_id.as_ref(), contributor.key().as_ref()],
+        bump,
+        has_one = contributor,
+    )]
+    pub contribution: Account<'info, Contribution>,
+    #[account(
+        mut,
+        seeds = [b"profile", contributor.key().as_ref()],
+        bump,
+        has_one = contributor,
+    )]
+    pub profile: Account<'info, ContributorProfile>,
+    #[account(mut)]
+    pub contributor: Signer<'info>,
+    /// CHECK: This account is used as a PDA for vault operations
+    #[account(
+        seeds = [b"vault", meme_id.as_ref()],
+        bump,
+        constraint = registry.meme_id == meme_id @ MemeError::InvalidMemeId
+    )]
+    pub vault: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault,
+    )]
+    pub vault_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = contributor,
+        associated_token::mint = mint,
+        associated_token::authority = contributor,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(address = registry.mint)]
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(meme_id: [u8; 16])]
+pub struct ExtendRegistryDeadline<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry", meme_id.as_ref()],
+        bump,
+    )]
+    pub registry: Account<'info, MemeRegistry>,
+    #[account(seeds = [b"state"], bump, has_one = authority)]
+    pub state: Account<'info, State>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(meme_id: [u8; 16])]
+pub struct CloseRegistryEarly<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry", meme_id.as_ref()],
+        bump,
+    )]
+    pub registry: Account<'info, MemeRegistry>,
+    #[account(seeds = [b"state"], bump, has_one = authority)]
+    pub state: Account<'info, State>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(meme_id: [u8; 16])]
+pub struct CancelMemeRegistry<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry", meme_id.as_ref()],
+        bump,
+    )]
+    pub registry: Account<'info, MemeRegistry>,
+    #[account(seeds = [b"state"], bump, has_one = authority)]
+    pub state: Account<'info, State>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(meme_id: [u8; 16])]
+pub struct PayReferral<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry", meme_id.as_ref()],
+        bump,
+    )]
+    pub registry: Account<'info, MemeRegistry>,
+    #[account(
+        mut,
+        seeds = [b"contribution", meme_id.as_ref(), contribution.contributor.as_ref()],
+        bump,
+        has_one = referrer @ MemeError::InvalidReferrer,
+    )]
+    pub contribution: Account<'info, Contribution>,
+    /// CHECK: This is safe because we're checking the address against the one recorded on the contribution
+    #[account(mut)]
+    pub referrer: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"referral", referrer.key().as_ref()],
+        bump,
+        has_one = referrer @ MemeError::InvalidReferrer,
+    )]
+    pub referral_stats: Account<'info, ReferralStats>,
+    /// CHECK: This account is a PDA, used as commission escrow
+    #[account(
+        mut,
+        seeds = [b"fee_escrow", meme_id.as_ref()],
+        bump,
+    )]
+    pub fee_escrow: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(meme_id: [u8; 16])]
+pub struct SettleContribution<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry", meme_id.as_ref()],
+        bump,
+    )]
+    pub registry: Account<'info, MemeRegistry>,
+    #[account(
+        mut,
+        seeds = [b"contribution", meme_id.as_ref(), contributor.key().as_ref()],
+        bump,
+        has_one = contributor,
+    )]
+    pub contribution: Account<'info, Contribution>,
+    #[account(
+        mut,
+        seeds = [b"profile", contributor.key().as_ref()],
+        bump,
+        has_one = contributor,
+    )]
+    pub profile: Account<'info, ContributorProfile>,
+    #[account(mut)]
+    pub contributor: Signer<'info>,
+    /// CHECK: This account is a PDA, used as vault
+    #[account(
+        mut,
+        seeds = [b"vault", meme_id.as_ref()],
+        bump,
+    )]
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: This account is a PDA, used as commission escrow
+    #[account(
+        mut,
+        seeds = [b"fee_escrow", meme_id.as_ref()],
+        bump,
+    )]
+    pub fee_escrow: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(meme_id: [u8; 16])]
+pub struct RefundContribution<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry", meme_id.as_ref()],
+        bump,
+    )]
+    pub registry: Account<'info, MemeRegistry>,
+    #[account(
+        mut,
+        seeds = [b"contribution", meme_id.as_ref(), contributor.key().as_ref()],
+        bump,
+        has_one = contributor,
+    )]
+    pub contribution: Account<'info, Contribution>,
+    #[account(
+        mut,
+        seeds = [b"profile", contributor.key().as_ref()],
+        bump,
+        has_one = contributor,
+    )]
+    pub profile: Account<'info, ContributorProfile>,
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub contributor: Signer<'info>,
+    /// CHECK: This account is a PDA, used as vault
+    #[account(
+        mut,
+        seeds = [b"vault", meme_id.as_ref()],
+        bump,
+    )]
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: This account is a PDA, used as commission escrow
+    #[account(
+        mut,
+        seeds = [b"fee_escrow", meme_id.as_ref()],
+        bump,
+    )]
+    pub fee_escrow: UncheckedAccount<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 #[instruction(meme_id: [u8; 16])]
-pub struct CreateMemeRegistry<'info> {
+pub struct AdminClaimRewards<'info> {
     #[account(
-        init,
-        payer = authority,
-        space = 8 + 16 + 8 + 8 + 8 + 32 + 8 + 32 + 8 + 8, // discriminator + meme_id + total_funds + start_time + end_time + authority +  contributor_count + mint + unclaimed_rewards + claimed_count
+        mut,
         seeds = [b"registry", meme_id.as_ref()],
-        bump
+        bump,
     )]
     pub registry: Account<'info, MemeRegistry>,
-    /// CHECK: This account is only used as a PDA for receiving SOL
+    #[account(seeds = [b"state"], bump, has_one = authority)]
+    pub state: Account<'info, State>,
+    /// CHECK: This account is a PDA, used as vault
     #[account(
+        mut,
         seeds = [b"vault", meme_id.as_ref()],
         bump,
     )]
     pub vault: UncheckedAccount<'info>,
+    /// CHECK: This account is a PDA, used as commission escrow
     #[account(
-        seeds = [b"state"],
+        mut,
+        seeds = [b"fee_escrow", meme_id.as_ref()],
         bump,
-        has_one = authority
     )]
-    pub state: Account<'info, State>,
-    #[account(mut)]
+    pub fee_escrow: UncheckedAccount<'info>,
     pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(meme_id: [u8; 16])]
-pub struct Contribute<'info> {
-    /// CHECK: This account is only used as a PDA for receiving SOL
+pub struct UpdateFeeSplit<'info> {
     #[account(
         mut,
-        seeds = [b"vault", meme_id.as_ref()],
+        seeds = [b"state"],
         bump,
+        has_one = authority,
     )]
-    pub vault: UncheckedAccount<'info>,
+    pub state: Account<'info, State>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMaxBuyAmount<'info> {
     #[account(
         mut,
-        seeds = [b"registry", meme_id.as_ref()],
-        bump,
-    )]
-    pub registry: Account<'info, MemeRegistry>,
-    #[account(
-        init,
-        payer = contributor,
-        space = 8 + 16 + 32 + 8 + 8 + 1, // discriminator + meme_id + contributor + amount + timestamp + is_claimed
-        seeds = [b"contribution", meme_id.as_ref(), contributor.key().as_ref()],
-        bump
-    )]
-    pub contribution: Account<'info, Contribution>,
-    #[account(mut)]
-    pub contributor: Signer<'info>,
-    #[account(
         seeds = [b"state"],
-        bump
+        bump,
+        has_one = authority,
     )]
     pub state: Account<'info, State>,
-    /// CHECK: This is safe because we're checking the address against the one stored in the state account
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMinBuyAmount<'info> {
     #[account(
         mut,
-        address = state.fee_recipient @ MemeError::InvalidFeeRecipient
+        seeds = [b"state"],
+        bump,
+        has_one = authority,
     )]
-    pub fee_recipient: UncheckedAccount<'info>,
-    pub system_program: Program<'info, System>,
+    pub state: Account<'info, State>,
+    pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct StartMeme<'info> {
-    #[account(mut)]
-    pub registry: Account<'info, MemeRegistry>,
-    /// CHECK: This account is used as a PDA for receiving and sending SOL
+pub struct UpdateFundDuration<'info> {
     #[account(
         mut,
-        seeds = [b"vault", registry.meme_id.as_ref()],
-        bump
+        seeds = [b"state"],
+        bump,
+        has_one = authority,
     )]
-    pub vault: UncheckedAccount<'info>,
-    #[account(mut)]
-    pub mint: Signer<'info>,
-    /// CHECK: This account is checked in the instruction
-    #[account(mut)]
-    pub mint_authority: UncheckedAccount<'info>,
-    /// CHECK: This account is checked in the instruction
-    #[account(mut)]
-    pub bonding_curve: UncheckedAccount<'info>,
-    /// CHECK: This account is checked in the instruction
-    #[account(mut)]
-    pub associated_bonding_curve: UncheckedAccount<'info>,
-    /// CHECK: This account is checked in the instruction
-    pub global: UncheckedAccount<'info>,
-    /// CHECK: This account is checked in the instruction
-    pub mpl_token_metadata: UncheckedAccount<'info>,
-    /// CHECK: This account is checked in the instruction
-    #[account(mut)]
-    pub metadata: UncheckedAccount<'info>,
+    pub state: Account<'info, State>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMaxFundLimit<'info> {
     #[account(
         mut,
-        constraint = authority.key() == registry.authority
+        seeds = [b"state"],
+        bump,
+        has_one = authority
     )]
+    pub state: Account<'info, State>,
     pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    pub associated_token_program: UncheckedAccount<'info>,
-    pub rent: Sysvar<'info, Rent>,
-    /// CHECK: This account is checked in the instruction
-    pub event_authority: UncheckedAccount<'info>,
-    /// CHECK: This account is checked in the instruction
-    pub pump_program: UncheckedAccount<'info>,
-    /// CHECK: This account is checked in the instruction
-    #[account(mut)]
-    pub fee_recipient: UncheckedAccount<'info>,
-    /// CHECK: This account is checked in the instruction
-    #[account(mut)]
-    pub associated_user: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
-#[instruction(meme_id: [u8; 16])]
-pub struct ClaimTokens<'info> {
+pub struct UpdateCommissionRate<'info> {
     #[account(
         mut,
-        seeds = [b"registry", meme_id.as_ref()],
+        seeds = [b"state"],
         bump,
+        has_one = authority,
     )]
-    pub registry: Account<'info, MemeRegistry>,
+    pub state: Account<'info, State>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateState<'info> {
+    /// CHECK: Legacy state layout, validated and rewritten in the instruction
     #[account(
         mut,
-        seeds = [b"contribution", meme_id.as_ref(), contributor.key().as_ref()],
+        seeds = [b"state"],
         bump,
-        has_one = contributor,
     )]
-    pub contribution: Account<'info, Contribution>,
+    pub state: UncheckedAccount<'info>,
     #[account(mut)]
-    pub contributor: Signer<'info>,
-    /// CHECK: This account is used as a PDA for vault operations
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(meme_id: [u8; 16])]
+pub struct MigrateRegistry<'info> {
+    /// CHECK: Legacy registry layout, validated and rewritten in the instruction
     #[account(
-        seeds = [b"vault", meme_id.as_ref()],
+        mut,
+        seeds = [b"registry", meme_id.as_ref()],
         bump,
-        constraint = registry.meme_id == meme_id @ MemeError::InvalidMemeId
     )]
-    pub vault: UncheckedAccount<'info>,
+    pub registry: UncheckedAccount<'info>,
+    /// CHECK: This account is a PDA, used as commission escrow
     #[account(
         mut,
-        associated_token::mint = mint,
-        associated_token::authority = vault,
-    )]
-    pub vault_token_account: Account<'info, TokenAccount>,
-    #[account(
-        init_if_needed,
-        payer = contributor,
-        associated_token::mint = mint,
-        associated_token::authority = contributor,
-    )]
-    pub user_token_account: Account<'info, TokenAccount>,
-    #[account(address = registry.mint)]
-    pub mint: Account<'info, Mint>,
-    #[account(
-        seeds = [b"state"],
+        seeds = [b"fee_escrow", meme_id.as_ref()],
         bump
     )]
+    pub fee_escrow: UncheckedAccount<'info>,
+    #[account(seeds = [b"state"], bump, has_one = authority)]
     pub state: Account<'info, State>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 #[instruction(meme_id: [u8; 16])]
-pub struct AdminClaimRewards<'info> {
+pub struct MigrateContribution<'info> {
+    /// CHECK: Checked to have the current layout in the instruction
     #[account(
-        mut,
         seeds = [b"registry", meme_id.as_ref()],
         bump,
     )]
-    pub registry: Account<'info, MemeRegistry>,
-    #[account(seeds = [b"state"], bump, has_one = authority)]
-    pub state: Account<'info, State>,
-    /// CHECK: This account is a PDA, used as vault
+    pub registry: UncheckedAccount<'info>,
+    /// CHECK: Legacy contribution layout, validated and rewritten in the instruction
     #[account(
         mut,
-        seeds = [b"vault", meme_id.as_ref()],
+        seeds = [b"contribution", meme_id.as_ref(), contributor.key().as_ref()],
         bump,
     )]
-    pub vault: UncheckedAccount<'info>,
-    /// CHECK: This is the fee recipient account, a normal wallet
-    #[account(mut, address = state.fee_recipient @ MemeError::InvalidFeeRecipient)]
-    pub fee_recipient: UncheckedAccount<'info>,
+    pub contribution: UncheckedAccount<'info>,
+    /// CHECK: Only used as the key owning the contribution and its profile
+    pub contributor: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + 32 + 8 + 8 + 8 + 8, // discriminator + contributor + lifetime_contributed + active_exposure + registries_joined + claims_made
+        seeds = [b"profile", contributor.key().as_ref()],
+        bump
+    )]
+    pub profile: Account<'info, ContributorProfile>,
+    #[account(seeds = [b"state"], bump, has_one = authority)]
+    pub state: Account<'info, State>,
+    #[account(mut)]
     pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateFeeRecipient<'info> {
+pub struct UpdateMaxClaimAvailableTime<'info> {
     #[account(
         mut,
         seeds = [b"state"],
@@ -841,7 +3787,7 @@ pub struct UpdateFeeRecipient<'info> {
 }
 
 #[derive(Accounts)]
-pub struct UpdateMaxBuyAmount<'info> {
+pub struct UpdateSoftCap<'info> {
     #[account(
         mut,
         seeds = [b"state"],
@@ -853,7 +3799,7 @@ pub struct UpdateMaxBuyAmount<'info> {
 }
 
 #[derive(Accounts)]
-pub struct UpdateMinBuyAmount<'info> {
+pub struct UpdateMaxActiveExposure<'info> {
     #[account(
         mut,
         seeds = [b"state"],
@@ -865,7 +3811,7 @@ pub struct UpdateMinBuyAmount<'info> {
 }
 
 #[derive(Accounts)]
-pub struct UpdateFundDuration<'info> {
+pub struct UpdateBuySlippage<'info> {
     #[account(
         mut,
         seeds = [b"state"],
@@ -877,19 +3823,19 @@ pub struct UpdateFundDuration<'info> {
 }
 
 #[derive(Accounts)]
-pub struct UpdateMaxFundLimit<'info> {
+pub struct UpdateReferralShare<'info> {
     #[account(
         mut,
         seeds = [b"state"],
         bump,
-        has_one = authority
+        has_one = authority,
     )]
     pub state: Account<'info, State>,
     pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateCommissionRate<'info> {
+pub struct UpdateMaxFundDuration<'info> {
     #[account(
         mut,
         seeds = [b"state"],
@@ -900,57 +3846,161 @@ pub struct UpdateCommissionRate<'info> {
     pub authority: Signer<'info>,
 }
 
-#[derive(Accounts)]
-pub struct UpdateMaxClaimAvailableTime<'info> {
-    #[account(
-        mut,
-        seeds = [b"state"],
-        bump,
-        has_one = authority,
-    )]
-    pub state: Account<'info, State>,
-    pub authority: Signer<'info>,
+// Events
+#[event]
+pub struct MemeRegistryCreated {
+    pub meme_id: [u8; 16],
+    pub start_time: i64,
+    pub end_time: i64,
+    pub params: RegistryParams,
+    pub phase_count: u8,
+}
+
+#[event]
+pub struct ContributionMade {
+    pub meme_id: [u8; 16],
+    pub contributor: Pubkey,
+    pub payer: Pubkey,
+    pub requested_amount: u64,
+    pub amount: u64,
+    pub commission_amount: u64,
+    pub referral_amount: u64,
+    pub net_contribution_amount: u64,
+    pub deadline_extension: i64,
+    pub end_time: i64,
+    pub phase_index: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MemeRegistryFull {
+    pub meme_id: [u8; 16],
+    pub total_funds: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MemeStarted {
+    pub meme_id: [u8; 16],
+    pub mint: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub total_funds: u64,
+    pub soft_cap: u64,
+    pub buy_amount: u64,
+    pub max_sol_cost: u64,
+    pub launched_at_slot: u64,
+}
+
+#[event]
+pub struct LaunchTokenCreated {
+    pub meme_id: [u8; 16],
+    pub mint: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LaunchBuyCompleted {
+    pub meme_id: [u8; 16],
+    pub mint: Pubkey,
+    pub total_funds: u64,
+    pub soft_cap: u64,
+    pub buy_amount: u64,
+    pub max_sol_cost: u64,
+    pub launched_at_slot: u64,
+}
+
+#[event]
+pub struct TrancheExecuted {
+    pub meme_id: [u8; 16],
+    pub tranche_index: u32,
+    pub buy_amount: u64,
+    pub sol_spent: u64,
+    pub tokens_bought: u64,
+    pub total_sol_spent: u64,
+    pub is_final: bool,
+    pub slot: u64,
+}
+
+#[event]
+pub struct TranchesFinished {
+    pub meme_id: [u8; 16],
+    pub tranche_count: u32,
+    pub tokens_bought: u64,
+    pub unspent_lamports: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct CommissionReleased {
+    pub meme_id: [u8; 16],
+    pub amount: u64,
+}
+
+#[event]
+pub struct ContributionSettled {
+    pub meme_id: [u8; 16],
+    pub contributor: Pubkey,
+    pub allocation: u64,
+    pub refunded_amount: u64,
+    pub refunded_commission: u64,
+}
+
+#[event]
+pub struct TokensClaimed {
+    pub meme_id: [u8; 16],
+    pub contributor: Pubkey,
+    pub amount: u64,
 }
 
-// Events
 #[event]
-pub struct MemeRegistryCreated {
+pub struct RegistryStatusChanged {
     pub meme_id: [u8; 16],
-    pub start_time: i64,
-    pub end_time: i64,
+    pub old_status: RegistryStatus,
+    pub new_status: RegistryStatus,
+    pub timestamp: i64,
 }
 
 #[event]
-pub struct ContributionMade {
+pub struct ContributionRefunded {
     pub meme_id: [u8; 16],
     pub contributor: Pubkey,
     pub amount: u64,
     pub commission_amount: u64,
-    pub net_contribution_amount: u64,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct MemeStarted {
+pub struct RegistryDeadlineExtended {
+    pub meme_id: [u8; 16],
+    pub old_end_time: i64,
+    pub new_end_time: i64,
+}
+
+#[event]
+pub struct RegistryClosedEarly {
     pub meme_id: [u8; 16],
-    pub mint: Pubkey,
-    pub name: String,
-    pub symbol: String,
-    pub uri: String,
     pub total_funds: u64,
+    pub old_end_time: i64,
+    pub timestamp: i64,
 }
 
 #[event]
-pub struct TokensClaimed {
+pub struct MemeRegistryCancelled {
     pub meme_id: [u8; 16],
-    pub contributor: Pubkey,
-    pub amount: u64,
+    pub total_funds: u64,
+    pub escrowed_commission: u64,
+    pub timestamp: i64,
 }
 
 #[event]
-pub struct FeeRecipientUpdated {
-    pub old_wallet: Pubkey,
-    pub new_wallet: Pubkey,
+pub struct FeeSplitUpdated {
+    pub old_split: Vec<FeeSplit>,
+    pub new_split: Vec<FeeSplit>,
 }
 
 #[event]
@@ -979,8 +4029,67 @@ pub struct MaxFundLimitUpdated {
 
 #[event]
 pub struct CommissionRateUpdated {
-    pub old_rate: u8,
-    pub new_rate: u8,
+    pub old_bps: u16,
+    pub new_bps: u16,
+}
+
+#[event]
+pub struct StateMigrated {
+    pub authority: Pubkey,
+    pub commission_bps: u16,
+}
+
+#[event]
+pub struct RegistryMigrated {
+    pub meme_id: [u8; 16],
+    pub status: RegistryStatus,
+}
+
+#[event]
+pub struct ContributionMigrated {
+    pub meme_id: [u8; 16],
+    pub contributor: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct SoftCapUpdated {
+    pub old_soft_cap: u64,
+    pub new_soft_cap: u64,
+}
+
+#[event]
+pub struct MaxActiveExposureUpdated {
+    pub old_limit: u64,
+    pub new_limit: u64,
+}
+
+#[event]
+pub struct BuySlippageUpdated {
+    pub old_slippage_bps: u16,
+    pub new_slippage_bps: u16,
+}
+
+#[event]
+pub struct ReferralShareUpdated {
+    pub old_share_bps: u16,
+    pub new_share_bps: u16,
+}
+
+#[event]
+pub struct ReferralPaid {
+    pub meme_id: [u8; 16],
+    pub referrer: Pubkey,
+    pub contributor: Pubkey,
+    pub amount: u64,
+    pub total_earned: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MaxFundDurationUpdated {
+    pub old_duration: i64,
+    pub new_duration: i64,
 }
 
 #[error_code]
@@ -1039,6 +4148,136 @@ pub enum MemeError {
     BelowMinAllowedAmount,
     #[msg("Commission rate cannot exceed 10%")]
     CommissionRateTooHigh,
+    #[msg("Meme has already been started")]
+    MemeAlreadyStarted,
+    #[msg("Refund is not available yet")]
+    RefundNotAvailable,
+    #[msg("Contribution has already been refunded")]
+    AlreadyRefunded,
+    #[msg("Invalid registry status for this operation")]
+    InvalidRegistryStatus,
+    #[msg("Insufficient fee escrow balance")]
+    InsufficientEscrowBalance,
+    #[msg("Max fund limit must be greater than 0 and cannot exceed 20 SOL")]
+    InvalidMaxFundLimit,
+    #[msg("Token claim available time cannot exceed 24 hours")]
+    InvalidClaimAvailableTime,
+    #[msg("Start time cannot be in the past")]
+    InvalidStartTime,
+    #[msg("Fund has not started yet")]
+    FundNotStarted,
+    #[msg("New end time must be later than the current end time")]
+    InvalidEndTime,
+    #[msg("Total fund duration exceeds the configured maximum")]
+    ExceedsMaxFundDuration,
+    #[msg("Invalid soft close window")]
+    InvalidSoftClose,
+    #[msg("Soft cap cannot exceed the max fund limit")]
+    InvalidSoftCap,
+    #[msg("Soft cap has not been reached")]
+    SoftCapNotReached,
+    #[msg("Launch spent SOL reserved for oversubscription refunds")]
+    VaultReserveSpent,
+    #[msg("Meme registry was not oversubscribed")]
+    NotOversubscribed,
+    #[msg("Contribution has already been settled")]
+    AlreadySettled,
+    #[msg("Contribution must be settled before claiming")]
+    ContributionNotSettled,
+    #[msg("Invalid allowlist configuration")]
+    InvalidAllowlist,
+    #[msg("Contributor is not on the allowlist")]
+    NotAllowlisted,
+    #[msg("Contribution exceeds the allowlist cap")]
+    ExceedsAllowlistCap,
+    #[msg("Invalid funding phase configuration")]
+    InvalidFundingPhase,
+    #[msg("No funding phase is active")]
+    NoActivePhase,
+    #[msg("Funding phase cap has been reached")]
+    PhaseCapReached,
+    #[msg("Contribution exceeds the maximum open exposure per wallet")]
+    ExceedsMaxExposure,
+    #[msg("Max exposure must be greater than 0")]
+    InvalidMaxExposure,
+    #[msg("Referral share cannot exceed 100%")]
+    InvalidReferralShare,
+    #[msg("Referrer and referral stats accounts must be passed together")]
+    InvalidReferralAccounts,
+    #[msg("Contributors cannot refer themselves")]
+    SelfReferral,
+    #[msg("Contribution is already attributed to a different referrer")]
+    ReferrerMismatch,
+    #[msg("Fee split must have 1 to 4 unique recipients with weights summing to 10000 bps")]
+    InvalidFeeSplit,
+    #[msg("Invalid state account")]
+    InvalidStateAccount,
+    #[msg("State account has already been migrated")]
+    StateAlreadyMigrated,
+    #[msg("Contribution has no referral commission to pay")]
+    NoReferralCommission,
+    #[msg("Referral commission has already been paid")]
+    ReferralAlreadyPaid,
+    #[msg("Referrer does not match the contribution")]
+    InvalidReferrer,
+    #[msg("Invalid pump program")]
+    InvalidPumpProgram,
+    #[msg("Invalid pump global account")]
+    InvalidPumpGlobal,
+    #[msg("Invalid pump mint authority")]
+    InvalidMintAuthority,
+    #[msg("Invalid bonding curve account")]
+    InvalidBondingCurve,
+    #[msg("Invalid associated bonding curve account")]
+    InvalidAssociatedBondingCurve,
+    #[msg("Invalid token metadata program")]
+    InvalidMetadataProgram,
+    #[msg("Invalid metadata account")]
+    InvalidMetadata,
+    #[msg("Invalid pump event authority")]
+    InvalidEventAuthority,
+    #[msg("Invalid pump fee recipient")]
+    InvalidPumpFeeRecipient,
+    #[msg("Invalid vault token account")]
+    InvalidAssociatedUser,
+    #[msg("Buy slippage cannot exceed 10%")]
+    InvalidBuySlippage,
+    #[msg("Bonding curve is already complete")]
+    BondingCurveComplete,
+    #[msg("Vault balance is too low to buy any tokens")]
+    BuyAmountTooSmall,
+    #[msg("Funding round is still open")]
+    FundingRoundNotClosed,
+    #[msg("Meme has already been launched")]
+    MemeAlreadyLaunched,
+    #[msg("Meme registry has been cancelled")]
+    RegistryCancelled,
+    #[msg("Invalid tranche parameters")]
+    InvalidTrancheParams,
+    #[msg("All tranches have already been bought")]
+    TranchesComplete,
+    #[msg("Tranche interval has not passed yet")]
+    TrancheIntervalNotReached,
+    #[msg("Tranche buying is still in progress")]
+    TranchesPending,
+    #[msg("Funding target not reached")]
+    FundingTargetNotReached,
+    #[msg("Invalid beneficiary")]
+    InvalidBeneficiary,
+    #[msg("Payout would leave the recipient below the rent exempt minimum")]
+    RecipientNotRentExempt,
+    #[msg("Bonding curve price is above the reference price")]
+    PriceAboveReference,
+    #[msg("Invalid mint")]
+    InvalidMint,
+    #[msg("Account is not a legacy account of this program")]
+    InvalidLegacyAccount,
+    #[msg("Account has already been migrated")]
+    AccountAlreadyMigrated,
+    #[msg("Registry has to be migrated before its contributions")]
+    RegistryNotMigrated,
+    #[msg("Not all contributions have been refunded")]
+    NotAllContributionsRefunded,
 }
 
 #[cfg(test)]
@@ -1048,20 +4287,140 @@ mod tests {
     #[test]
     fn test_commission_calculation() {
         let amount: u64 = 1_000_000_000; // 1 SOL
-        let commission_rate: u8 = 5; // 5%
-        
-        let commission_amount = amount
-            .checked_mul(commission_rate as u64)
-            .unwrap()
-            .checked_div(100)
-            .unwrap();
+        let commission_bps: u16 = 500; // 5%
         
-        let net_contribution = amount
-            .checked_sub(commission_amount)
-            .unwrap();
+        let (commission_amount, net_contribution) = split_commission(amount, commission_bps).unwrap();
         
         assert_eq!(commission_amount, 50_000_000); // 0.05 SOL
         assert_eq!(net_contribution, 950_000_000); // 0.95 SOL
+
+        // Fractional rates such as 2.5% are expressible
+        let (commission_amount, _) = split_commission(amount, 250).unwrap();
+        assert_eq!(commission_amount, 25_000_000);
+
+        // Fractional lamports are rounded up in favour of the protocol
+        let (commission_amount, net_contribution) = split_commission(1_999, 250).unwrap();
+        assert_eq!(commission_amount, 50);
+        assert_eq!(net_contribution, 1_949);
+    }
+
+    #[test]
+    fn test_fill_to_capacity() {
+        let amount: u64 = 1_000_000_000; // 1 SOL
+        let commission_bps: u16 = 500; // 5%
+
+        // Enough capacity, the whole amount is accepted
+        assert_eq!(fill_to_capacity(amount, commission_bps, 10_000_000_000).unwrap(), amount);
+
+        // Only 0.5 SOL of net capacity left
+        let remaining_capacity: u64 = 500_000_000;
+        let accepted_amount = fill_to_capacity(amount, commission_bps, remaining_capacity).unwrap();
+        let (commission_amount, net_contribution_amount) = split_commission(accepted_amount, commission_bps).unwrap();
+
+        assert!(accepted_amount < amount);
+        assert!(net_contribution_amount <= remaining_capacity, "Net part must not overshoot the cap");
+        assert!(remaining_capacity - net_contribution_amount <= 1, "Capacity should be filled exactly");
+        assert_eq!(commission_amount, accepted_amount - net_contribution_amount);
+    }
+
+    #[test]
+    fn test_round_full_below_min_buy() {
+        let mut registry = MemeRegistry::deserialize(&mut &[0u8; 1024][..]).unwrap();
+        registry.params.min_buy_amount = 100_000_000; // 0.1 SOL
+        registry.params.max_fund_limit = 150_000_000; // 0.15 SOL
+        registry.params.commission_bps = 500; // 5%
+
+        // A minimum buy nets 0.095 SOL and still fits
+        registry.total_funds = 55_000_000;
+        assert!(!registry.is_full().unwrap());
+
+        // Less than that is left, so no further buy can be accepted
+        registry.total_funds = 95_000_000;
+        assert!(registry.is_full().unwrap());
+
+        registry.total_funds = 150_000_000;
+        assert!(registry.is_full().unwrap());
+
+        // A phase with a lower minimum keeps the round open
+        registry.total_funds = 95_000_000;
+        registry.phases = vec![FundingPhase {
+            start_time: 0,
+            end_time: 300,
+            min_buy_amount: 50_000_000,
+            max_buy_amount: 100_000_000,
+            phase_cap: 150_000_000,
+            allowlist_only: false,
+            total_funds: 0,
+        }];
+        assert!(!registry.is_full().unwrap());
+    }
+
+    #[test]
+    fn test_pro_rata_allocation() {
+        let cap: u64 = 10_000_000_000; // 10 SOL
+        let total_pledged: u64 = 25_000_000_000; // 25 SOL
+        let amount: u64 = 950_000_000; // 0.95 SOL net
+        let commission_amount: u64 = 50_000_000; // 0.05 SOL
+
+        let allocation = pro_rata_allocation(amount, cap, total_pledged).unwrap();
+        assert_eq!(allocation, 380_000_000); // 40% of the pledge
+
+        let excess_amount = amount - allocation;
+        let excess_commission = pro_rata_allocation(commission_amount, excess_amount, amount).unwrap();
+        assert_eq!(excess_commission, 30_000_000); // 60% of the commission
+
+        // The escrowed referral share is scaled down alongside the protocol commission
+        let referral_amount: u64 = 10_000_000; // 20% of the commission
+        let protocol_commission = commission_amount - referral_amount;
+        let excess_protocol_commission = pro_rata_allocation(protocol_commission, excess_amount, amount).unwrap();
+        let excess_referral_commission = pro_rata_allocation(referral_amount, excess_amount, amount).unwrap();
+        assert_eq!(excess_protocol_commission, 24_000_000);
+        assert_eq!(excess_referral_commission, 6_000_000);
+        assert_eq!(excess_protocol_commission + excess_referral_commission, excess_commission);
+    }
+
+    #[test]
+    fn test_oversubscribed_settlement() {
+        // Uneven pledges whose pro-rata shares all round, as (commission, net) pairs
+        let pledges: Vec<(u64, u64)> = [350_000_001, 1_000_000_003, 129_954_517, 818_713_451]
+            .iter()
+            .map(|&amount| split_commission(amount, 500).unwrap())
+            .collect();
+        let cap: u64 = 999_999_999;
+
+        let mut registry = MemeRegistry::deserialize(&mut &[0u8; 1024][..]).unwrap();
+        registry.params.oversubscription = true;
+        registry.params.max_fund_limit = cap;
+        registry.total_funds = pledges.iter().map(|(_, net)| net).sum();
+        registry.total_commission = pledges.iter().map(|(commission, _)| commission).sum();
+        registry.begin_launch(0).unwrap();
+
+        // The launch releases the commission earned on the cap
+        let total_commission = registry.total_commission;
+        let earned_commission = pro_rata_allocation(total_commission, registry.total_funds, registry.total_pledged).unwrap();
+        registry.total_commission -= earned_commission;
+
+        let reserved_excess = registry.reserved_excess();
+        let mut refunded: u64 = 0;
+        let mut allocated: u64 = 0;
+        let mut refunded_commission: u64 = 0;
+        for (commission, net) in pledges {
+            let settlement = registry.settle(net, commission, 0).unwrap();
+            assert!(settlement.excess_amount <= reserved_excess - refunded);
+            assert_eq!(settlement.allocation + settlement.excess_amount, net);
+
+            refunded += settlement.excess_amount;
+            allocated += settlement.allocation;
+            refunded_commission += settlement.excess_protocol_commission;
+        }
+
+        // Exactly the reserve is refunded, the allocations add up to the cap and the
+        // commission refunds stay within what the launch left in escrow
+        assert_eq!(refunded, reserved_excess);
+        assert_eq!(allocated, cap);
+        assert_eq!(registry.reserved_excess(), 0);
+        assert!(refunded_commission <= total_commission - earned_commission);
+        assert_eq!(registry.total_commission, total_commission - earned_commission - refunded_commission);
     }
 
     #[test]
@@ -1089,6 +4448,155 @@ mod tests {
         assert_eq!(user_tokens, 100_000); // User should get 10% of tokens
     }
 
+    #[test]
+    fn test_profile_exposure_release() {
+        let mut profile = ContributorProfile {
+            contributor: Pubkey::new_unique(),
+            lifetime_contributed: 3_000_000_000,
+            active_exposure: 2_000_000_000,
+            registries_joined: 3,
+            claims_made: 1,
+        };
+
+        profile.release_exposure(1_500_000_000);
+        assert_eq!(profile.active_exposure, 500_000_000);
+
+        // Releasing more than the open exposure never underflows
+        profile.release_exposure(1_000_000_000);
+        assert_eq!(profile.active_exposure, 0);
+        assert_eq!(profile.lifetime_contributed, 3_000_000_000);
+    }
+
+    #[test]
+    fn test_referral_share() {
+        let (commission, _) = split_commission(1_000_000_000, 500).unwrap();
+        assert_eq!(commission, 50_000_000);
+
+        // A 20% share of the commission goes to the referrer
+        let referral = pro_rata_allocation(commission, 2_000, BPS_DENOMINATOR).unwrap();
+        assert_eq!(referral, 10_000_000);
+
+        // Shares round down in favour of the protocol
+        assert_eq!(pro_rata_allocation(9_999, 1, BPS_DENOMINATOR).unwrap(), 0);
+        assert_eq!(pro_rata_allocation(commission, BPS_DENOMINATOR, BPS_DENOMINATOR).unwrap(), commission);
+    }
+
+    #[test]
+    fn test_fee_split() {
+        let treasury = Pubkey::new_unique();
+        let creator = Pubkey::new_unique();
+        let community = Pubkey::new_unique();
+        let split = vec![
+            FeeSplit { wallet: treasury, bps: 5_000 },
+            FeeSplit { wallet: creator, bps: 3_333 },
+            FeeSplit { wallet: community, bps: 1_667 },
+        ];
+        assert!(validate_fee_split(&split).is_ok());
+
+        // The last recipient takes the rounding remainder
+        let amounts = fee_split_amounts(1_000_001, &split).unwrap();
+        assert_eq!(amounts, vec![500_000, 333_300, 166_701]);
+        assert_eq!(amounts.iter().sum::<u64>(), 1_000_001);
+
+        // Weights must sum to exactly 100%
+        let short = vec![FeeSplit { wallet: treasury, bps: 9_999 }];
+        assert!(validate_fee_split(&short).is_err());
+
+        // Duplicate wallets and empty tables are rejected
+        let duplicate = vec![
+            FeeSplit { wallet: treasury, bps: 5_000 },
+            FeeSplit { wallet: treasury, bps: 5_000 },
+        ];
+        assert!(validate_fee_split(&duplicate).is_err());
+        assert!(validate_fee_split(&[]).is_err());
+
+        let too_many: Vec<FeeSplit> = (0..5)
+            .map(|_| FeeSplit { wallet: Pubkey::new_unique(), bps: 2_000 })
+            .collect();
+        assert!(validate_fee_split(&too_many).is_err());
+    }
+
+    #[test]
+    fn test_funding_phases() {
+        let public_phase = FundingPhase {
+            start_time: 0,
+            end_time: 200,
+            min_buy_amount: MIN_SOL_AMOUNT,
+            max_buy_amount: 1_000_000_000,
+            phase_cap: 5_000_000_000,
+            allowlist_only: false,
+            total_funds: 0,
+        };
+        let last_call_phase = FundingPhase {
+            start_time: 200,
+            end_time: 300,
+            max_buy_amount: 500_000_000,
+            ..public_phase
+        };
+        let params = RegistryParams {
+            fund_duration: 300,
+            min_buy_amount: MIN_SOL_AMOUNT,
+            max_buy_amount: MAX_SOL_AMOUNT,
+            max_fund_limit: MAX_FUND_LIMIT,
+            commission_bps: 500,
+            token_claim_available_time: 0,
+            soft_cap: 0,
+            oversubscription: false,
+            referral_share_bps: 0,
+            buy_slippage_bps: DEFAULT_BUY_SLIPPAGE_BPS,
+            max_fund_duration: MAX_TOTAL_FUND_DURATION,
+        };
+        let no_allowlist = AllowlistParams::default();
+        let phases = [public_phase, last_call_phase];
+
+        assert!(validate_phases(&phases, 0, 300, &params, &no_allowlist).is_ok());
+
+        // Overlapping phases and a last phase not matching the deadline are rejected
+        let overlapping = FundingPhase { start_time: 150, ..last_call_phase };
+        assert!(validate_phases(&[public_phase, overlapping], 0, 300, &params, &no_allowlist).is_err());
+        assert!(validate_phases(&[public_phase], 0, 300, &params, &no_allowlist).is_err());
+
+        // Phases cannot loosen the registry limits
+        let whale_phase = FundingPhase { max_buy_amount: MAX_SOL_AMOUNT + 1, ..last_call_phase };
+        assert!(validate_phases(&[public_phase, whale_phase], 0, 300, &params, &no_allowlist).is_err());
+
+        // Allowlist phases need a merkle root
+        let allowlist_phase = FundingPhase { allowlist_only: true, ..public_phase };
+        assert!(validate_phases(&[allowlist_phase, last_call_phase], 0, 300, &params, &no_allowlist).is_err());
+        let allowlist = AllowlistParams { merkle_root: [1u8; 32], end_time: 200 };
+        assert!(validate_phases(&[allowlist_phase, last_call_phase], 0, 300, &params, &allowlist).is_ok());
+
+        assert_eq!(active_phase(&phases, 300, 0), Some(0));
+        assert_eq!(active_phase(&phases, 300, 250), Some(1));
+        assert_eq!(active_phase(&phases, 300, 300), None);
+        assert_eq!(active_phase(&[], 300, 0), None);
+
+        // The last phase follows an extended deadline
+        assert_eq!(active_phase(&phases, 360, 330), Some(1));
+    }
+
+    #[test]
+    fn test_allowlist_merkle_proof() {
+        let member = Pubkey::new_unique();
+        let other_member = Pubkey::new_unique();
+        let outsider = Pubkey::new_unique();
+
+        let leaf = allowlist_leaf(&member, 500_000_000);
+        let sibling = allowlist_leaf(&other_member, 0);
+        let root = if leaf <= sibling {
+            keccak::hashv(&[&leaf, &sibling]).to_bytes()
+        } else {
+            keccak::hashv(&[&sibling, &leaf]).to_bytes()
+        };
+
+        assert!(verify_merkle_proof(&[sibling], root, leaf));
+        assert!(verify_merkle_proof(&[leaf], root, sibling));
+
+        // Wrong cap or wallet must not verify
+        assert!(!verify_merkle_proof(&[sibling], root, allowlist_leaf(&member, 0)));
+        assert!(!verify_merkle_proof(&[sibling], root, allowlist_leaf(&outsider, 500_000_000)));
+    }
+
     #[test]
     fn test_pda_derivation() {
         let program_id = Pubkey::new_unique();
@@ -1118,15 +4626,426 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_registry_params_validation() {
+        let whale_round = RegistryParams {
+            fund_duration: 600,
+            min_buy_amount: 1_000_000_000,
+            max_buy_amount: MAX_SOL_AMOUNT,
+            max_fund_limit: MAX_FUND_LIMIT,
+            commission_bps: 300,
+            token_claim_available_time: 900,
+            soft_cap: 5_000_000_000,
+            oversubscription: true,
+            referral_share_bps: 2_000,
+            buy_slippage_bps: DEFAULT_BUY_SLIPPAGE_BPS,
+            max_fund_duration: MAX_TOTAL_FUND_DURATION,
+        };
+        assert!(whale_round.validate().is_ok());
+
+        let micro_round = RegistryParams {
+            min_buy_amount: MIN_SOL_AMOUNT,
+            max_buy_amount: 200_000_000,
+            max_fund_limit: 2_000_000_000,
+            soft_cap: 1_000_000_000,
+            ..whale_round
+        };
+        assert!(micro_round.validate().is_ok());
+
+        let below_min = RegistryParams { min_buy_amount: MIN_SOL_AMOUNT - 1, ..micro_round };
+        assert!(below_min.validate().is_err());
+
+        let above_fund_limit = RegistryParams { max_fund_limit: MAX_FUND_LIMIT + 1, ..whale_round };
+        assert!(above_fund_limit.validate().is_err());
+
+        let commission_too_high = RegistryParams { commission_bps: MAX_COMMISSION_BPS + 1, ..whale_round };
+        assert!(commission_too_high.validate().is_err());
+
+        let claim_time_too_long = RegistryParams { token_claim_available_time: MAX_TOKEN_CLAIM_AVAILABLE_TIME + 1, ..whale_round };
+        assert!(claim_time_too_long.validate().is_err());
+
+        let soft_cap_above_limit = RegistryParams { soft_cap: micro_round.max_fund_limit + 1, ..micro_round };
+        assert!(soft_cap_above_limit.validate().is_err());
+
+        let referral_share_too_high = RegistryParams { referral_share_bps: BPS_DENOMINATOR as u16 + 1, ..whale_round };
+        assert!(referral_share_too_high.validate().is_err());
+
+        let slippage_too_high = RegistryParams { buy_slippage_bps: MAX_BUY_SLIPPAGE_BPS + 1, ..whale_round };
+        assert!(slippage_too_high.validate().is_err());
+
+        let duration_above_max = RegistryParams { max_fund_duration: whale_round.fund_duration - 1, ..whale_round };
+        assert!(duration_above_max.validate().is_err());
+    }
+
+    #[test]
+    fn test_state_migration_layout() {
+        let legacy = LegacyState {
+            fee_recipient: Pubkey::new_unique(),
+            max_buy_amount: MAX_SOL_AMOUNT,
+            min_buy_amount: MIN_SOL_AMOUNT,
+            authority: Pubkey::new_unique(),
+            fund_duration: 300,
+            max_fund_limit: MAX_FUND_LIMIT,
+            commission_rate: 5,
+            token_claim_available_time: 150,
+        };
+        assert_eq!(8 + legacy.try_to_vec().unwrap().len(), LEGACY_STATE_SIZE);
+
+        // A full fee split table still fits into the migrated account
+        let state = State {
+            fee_split: (0..MAX_FEE_RECIPIENTS)
+                .map(|_| FeeSplit { wallet: Pubkey::new_unique(), bps: 2_500 })
+                .collect(),
+            max_buy_amount: legacy.max_buy_amount,
+            min_buy_amount: legacy.min_buy_amount,
+            authority: legacy.authority,
+            fund_duration: legacy.fund_duration,
+            max_fund_limit: legacy.max_fund_limit,
+            commission_bps: legacy.commission_rate as u16 * 100,
+            token_claim_available_time: legacy.token_claim_available_time,
+            max_fund_duration: MAX_TOTAL_FUND_DURATION,
+            soft_cap: 0,
+            max_active_exposure: DEFAULT_MAX_ACTIVE_EXPOSURE,
+            referral_share_bps: 0,
+            buy_slippage_bps: DEFAULT_BUY_SLIPPAGE_BPS,
+        };
+        assert_eq!(8 + state.try_to_vec().unwrap().len(), STATE_SIZE);
+    }
+
+    #[test]
+    fn test_registry_migration() {
+        let end_time: i64 = 1_700_000_000;
+        let legacy = LegacyMemeRegistry {
+            meme_id: [7; 16],
+            total_funds: 5_000_000_000,
+            start_time: end_time - 300,
+            end_time,
+            authority: Pubkey::new_unique(),
+            contributor_count: 3,
+            mint: Pubkey::default(),
+            unclaimed_rewards: 0,
+            claimed_count: 0,
+        };
+        assert_eq!(8 + legacy.try_to_vec().unwrap().len(), LEGACY_REGISTRY_SIZE);
+
+        // The status follows the launch and claim progress
+        assert_eq!(legacy.status(end_time - 1), RegistryStatus::Open);
+        assert_eq!(legacy.status(end_time), RegistryStatus::Closed);
+        let launched = LegacyMemeRegistry { mint: Pubkey::new_unique(), ..legacy };
+        assert_eq!(launched.status(end_time), RegistryStatus::Launched);
+        let claiming = LegacyMemeRegistry { claimed_count: 2, ..launched };
+        assert_eq!(claiming.status(end_time), RegistryStatus::Claiming);
+        let finalized = LegacyMemeRegistry { claimed_count: 3, ..claiming };
+        assert_eq!(finalized.status(end_time), RegistryStatus::Finalized);
+
+        // A launched round can be claimed straight away, and the migrated registry fits
+        // into the account with a full phase table
+        let params = RegistryParams {
+            fund_duration: 300,
+            min_buy_amount: MIN_SOL_AMOUNT,
+            max_buy_amount: MAX_SOL_AMOUNT,
+            max_fund_limit: MAX_FUND_LIMIT,
+            commission_bps: 500,
+            token_claim_available_time: 0,
+            soft_cap: 0,
+            oversubscription: false,
+            referral_share_bps: 0,
+            buy_slippage_bps: DEFAULT_BUY_SLIPPAGE_BPS,
+            max_fund_duration: MAX_TOTAL_FUND_DURATION,
+        };
+        let mut registry = claiming.migrate(params, end_time);
+        assert!(registry.tranches_complete);
+        assert_eq!(registry.total_commission, 0);
+        assert_eq!(registry.original_end_time, end_time);
+
+        let phase = FundingPhase {
+            start_time: 0,
+            end_time: 0,
+            min_buy_amount: 0,
+            max_buy_amount: 0,
+            phase_cap: 0,
+            allowlist_only: false,
+            total_funds: 0,
+        };
+        registry.phases = vec![phase; MAX_FUNDING_PHASES];
+        assert_eq!(8 + registry.try_to_vec().unwrap().len(), REGISTRY_SIZE);
+    }
+
+    #[test]
+    fn test_contribution_migration() {
+        let legacy = LegacyContribution {
+            meme_id: [7; 16],
+            contributor: Pubkey::new_unique(),
+            amount: 950_000_000,
+            timestamp: 1_700_000_000,
+            is_claimed: false,
+        };
+        assert_eq!(8 + legacy.try_to_vec().unwrap().len(), LEGACY_CONTRIBUTION_SIZE);
+
+        // Legacy rounds have no funding phases, so no phase totals are carried over
+        let contribution = legacy.migrate();
+        assert_eq!(contribution.phase_contributed, [0; MAX_FUNDING_PHASES]);
+        assert_eq!(contribution.commission_amount, 0);
+        assert_eq!(contribution.first_contribution_time, legacy.timestamp);
+        assert_eq!(contribution.referrer, Pubkey::default());
+        assert_eq!(8 + contribution.try_to_vec().unwrap().len(), CONTRIBUTION_SIZE);
+    }
+
+    #[test]
+    fn test_pump_account_derivation() {
+        // The pump event authority is a PDA of the pump program
+        assert_eq!(
+            pump_pda(&[b"__event_authority"]),
+            pubkey!("Ce6TQqeHC9p8KetsN6JsjHK7UTZk7nasjjnr7XxXp9F1")
+        );
+
+        // Mint specific accounts differ between mints
+        let mint = Pubkey::new_unique();
+        let other_mint = Pubkey::new_unique();
+        assert_ne!(
+            pump_pda(&[b"bonding-curve", mint.as_ref()]),
+            pump_pda(&[b"bonding-curve", other_mint.as_ref()])
+        );
+        assert_ne!(metadata_pda(&mint), metadata_pda(&other_mint));
+    }
+
+    #[test]
+    fn test_pump_buy_sizing() {
+        let curve = PumpBondingCurve {
+            virtual_token_reserves: 1_073_000_000_000_000,
+            virtual_sol_reserves: 30_000_000_000,
+            real_token_reserves: 793_100_000_000_000,
+            real_sol_reserves: 0,
+            token_total_supply: 1_000_000_000_000_000,
+            complete: false,
+        };
+        let fee_basis_points: u64 = 100; // 1%
+        let spendable_lamports: u64 = 9_500_000_000; // 9.5 SOL
+
+        let (buy_amount, max_sol_cost) = size_pump_buy(spendable_lamports, fee_basis_points, &curve).unwrap();
+        assert!(buy_amount > 0);
+
+        // The max cost plus the pump fee fits into the spendable lamports
+        let max_fee = max_sol_cost * fee_basis_points / BPS_DENOMINATOR;
+        assert!(max_sol_cost + max_fee <= spendable_lamports);
+
+        // The cost the pump program charges for the sized amount stays within the max cost
+        let product = curve.virtual_sol_reserves as u128 * curve.virtual_token_reserves as u128;
+        let new_virtual_token_reserves = curve.virtual_token_reserves as u128 - buy_amount as u128;
+        let sol_cost = (product / new_virtual_token_reserves + 1 - curve.virtual_sol_reserves as u128 + 1) as u64;
+        assert!(sol_cost <= max_sol_cost);
+
+        // Nothing is bought without spendable lamports, and never more than the real reserves
+        assert_eq!(size_pump_buy(0, fee_basis_points, &curve).unwrap().0, 0);
+        let (whale_amount, _) = size_pump_buy(u64::MAX / 2, fee_basis_points, &curve).unwrap();
+        assert_eq!(whale_amount, curve.real_token_reserves);
+
+        // A bought out curve sizes every budget to nothing, which ends tranche buying
+        let drained = PumpBondingCurve { real_token_reserves: 0, ..curve };
+        assert_eq!(size_pump_buy(spendable_lamports, fee_basis_points, &drained).unwrap().0, 0);
+    }
+
+    #[test]
+    fn test_price_bound() {
+        // A fresh curve trades at the launch price
+        let launch_price = curve_price(30_000_000_000, 1_073_000_000_000_000).unwrap();
+        assert_eq!(launch_price, 27_958_993);
+        assert!(check_price(launch_price, launch_price, 0).is_ok());
+
+        // A buy placed in front pushes the price up
+        let front_run_price = curve_price(32_000_000_000, 1_005_937_500_000_000).unwrap();
+        assert!(front_run_price > launch_price);
+        assert!(check_price(front_run_price, launch_price, 100).is_err());
+        assert!(check_price(front_run_price, launch_price, MAX_BUY_SLIPPAGE_BPS).is_err());
+
+        // Small moves stay within the slippage
+        let nudged_price = launch_price + launch_price / 200;
+        assert!(check_price(nudged_price, launch_price, 100).is_ok());
+        assert!(check_price(nudged_price, launch_price, 0).is_err());
+    }
+
     #[test]
     fn test_commission_rate_validation() {
-        let test_rate: u8 = 15;
+        let test_bps: u16 = 1_500;
         assert!(
-            test_rate > MAX_COMMISSION_RATE,
+            test_bps > MAX_COMMISSION_BPS,
             "Commission rate exceeds maximum allowed"
         );
     }
 
+    #[test]
+    fn test_refund_gating() {
+        let end_time: i64 = 1_700_000_000;
+        let soft_cap: u64 = 2_000_000_000;
+
+        // Never while the round is still running
+        assert!(!refund_available(end_time, 0, soft_cap, end_time - 1).unwrap());
+
+        // A round that missed its soft cap is refundable at the deadline
+        assert!(refund_available(end_time, 1_000_000_000, soft_cap, end_time).unwrap());
+
+        // A successful round only once the grace period passed without a launch
+        assert!(!refund_available(end_time, soft_cap, soft_cap, end_time).unwrap());
+        assert!(!refund_available(end_time, soft_cap, soft_cap, end_time + REFUND_GRACE_PERIOD - 1).unwrap());
+        assert!(refund_available(end_time, soft_cap, soft_cap, end_time + REFUND_GRACE_PERIOD).unwrap());
+
+        // Without a soft cap only the grace period applies
+        assert!(!refund_available(end_time, 0, 0, end_time).unwrap());
+        assert!(refund_available(end_time, 0, 0, end_time + REFUND_GRACE_PERIOD).unwrap());
+    }
+
+    #[test]
+    fn test_registry_status_transition() {
+        // A zeroed account decodes as an empty open registry
+        let mut registry = MemeRegistry::deserialize(&mut &[0u8; 1024][..]).unwrap();
+        assert_eq!(registry.status, RegistryStatus::Open);
+
+        registry.set_status(RegistryStatus::Closed, 300);
+        assert_eq!(registry.status, RegistryStatus::Closed);
+
+        registry.set_status(RegistryStatus::Launched, 301);
+        assert_eq!(registry.status, RegistryStatus::Launched);
+    }
+
+    #[test]
+    fn test_launch_gating() {
+        let open = MemeRegistry::deserialize(&mut &[0u8; 1024][..]).unwrap();
+        let mut registry = open.clone();
+        registry.end_time = 300;
+        registry.total_funds = 1_000_000_000;
+
+        // Not before the deadline
+        assert!(registry.check_launchable(299).is_err());
+        assert_eq!(registry.status, RegistryStatus::Open);
+
+        // Once the deadline has passed the round closes and can be launched
+        assert!(registry.check_launchable(300).is_ok());
+        assert_eq!(registry.status, RegistryStatus::Closed);
+
+        // A round closed early by its hard cap can be launched before the deadline
+        let mut full = open.clone();
+        full.end_time = 300;
+        full.status = RegistryStatus::Closed;
+        assert!(full.check_launchable(100).is_ok());
+
+        // Never twice
+        registry.mint = Pubkey::new_unique();
+        registry.set_status(RegistryStatus::Launched, 301);
+        assert!(registry.check_launchable(302).is_err());
+
+        let mut cancelled = open.clone();
+        cancelled.set_status(RegistryStatus::Cancelled, 100);
+        assert!(cancelled.check_launchable(400).is_err());
+
+        let mut below_soft_cap = open;
+        below_soft_cap.params.soft_cap = 2_000_000_000;
+        below_soft_cap.total_funds = 1_000_000_000;
+        assert!(below_soft_cap.check_launchable(300).is_err());
+    }
+
+    #[test]
+    fn test_refund_amount() {
+        let total_funds: u64 = 4_000_000_000;
+
+        // A funded vault refunds every contribution in full
+        assert_eq!(refund_amount(1_000_000_000, total_funds, total_funds).unwrap(), 1_000_000_000);
+        assert_eq!(refund_amount(1_000_000_000, total_funds + 890_880, total_funds).unwrap(), 1_000_000_000);
+
+        // After a cancelled launch paid for the token creation the remainder is shared pro rata
+        let vault_lamports = total_funds - 40_000_000;
+        assert_eq!(refund_amount(1_000_000_000, vault_lamports, total_funds).unwrap(), 990_000_000);
+
+        // Refunding one contribution keeps the share of the others unchanged
+        let remaining = vault_lamports - 990_000_000;
+        assert_eq!(refund_amount(3_000_000_000, remaining, total_funds - 1_000_000_000).unwrap(), remaining);
+    }
+
+    #[test]
+    fn test_tranche_buying() {
+        let tranche = TrancheParams {
+            tranche_amount: 1_000_000_000,
+            interval_slots: 10,
+        };
+        assert!(tranche.validate().is_ok());
+        assert!(TrancheParams { tranche_amount: 1, ..tranche }.validate().is_err());
+        assert!(TrancheParams { interval_slots: 0, ..tranche }.validate().is_err());
+        assert!(TrancheParams { interval_slots: MAX_TRANCHE_INTERVAL_SLOTS + 1, ..tranche }.validate().is_err());
+
+        // Without tranches the whole vault is bought at once
+        assert_eq!(TrancheParams::default().next_tranche(3_000_000_000), (3_000_000_000, true));
+
+        // Full tranches, then the rest, with dust folded into the last tranche
+        assert_eq!(tranche.next_tranche(3_000_000_000), (1_000_000_000, false));
+        assert_eq!(tranche.next_tranche(1_500_000_000), (1_000_000_000, false));
+        assert_eq!(tranche.next_tranche(1_050_000_000), (1_050_000_000, true));
+        assert_eq!(tranche.next_tranche(400_000_000), (400_000_000, true));
+
+        // Buys accumulate on the registry until the last tranche
+        let mut registry = MemeRegistry::deserialize(&mut &[0u8; 1024][..]).unwrap();
+        registry.tranche = tranche;
+        let mut buy = PumpBuy {
+            buy_amount: 1_000,
+            max_sol_cost: 990_000_000,
+            sol_spent: 980_000_000,
+            is_final: false,
+            end_price: 1_000,
+        };
+        registry.record_buy(&buy, 100).unwrap();
+        assert_eq!(registry.reference_price, 1_000);
+        buy.is_final = true;
+        buy.end_price = 1_100;
+        registry.record_buy(&buy, 110).unwrap();
+        assert_eq!(registry.tokens_bought, 2_000);
+        assert_eq!(registry.sol_spent, 1_960_000_000);
+        assert_eq!(registry.tranche_count, 2);
+        assert_eq!(registry.last_tranche_slot, 110);
+        assert_eq!(registry.reference_price, 1_100);
+        assert!(registry.tranches_complete);
+
+        // Settled excess is no longer held back from tranche buys
+        registry.total_funds = 20_000_000_000;
+        registry.total_pledged = 30_000_000_000;
+        registry.settled_excess = 4_000_000_000;
+        assert_eq!(registry.reserved_excess(), 6_000_000_000);
+    }
+
+    #[test]
+    fn test_two_step_launch() {
+        let mut registry = MemeRegistry::deserialize(&mut &[0u8; 1024][..]).unwrap();
+        registry.status = RegistryStatus::Closed;
+        registry.total_funds = 1_000_000_000;
+
+        // The create step records the mint, a second create is rejected
+        assert!(registry.begin_launch(100).is_ok());
+        registry.mint = Pubkey::new_unique();
+        registry.set_status(RegistryStatus::Launching, 100);
+        assert!(registry.begin_launch(101).is_err());
+
+        // The buy step completes the launch
+        registry.mark_launched(42, 102);
+        assert_eq!(registry.status, RegistryStatus::Launched);
+        assert_eq!(registry.launched_at_slot, 42);
+        assert_eq!(registry.launched_at, 102);
+    }
+
+    #[test]
+    fn test_soft_close_extension() {
+        let soft_close = SoftCloseParams { window: 60, extension: 60, max_extension: 90 };
+
+        // Outside the window nothing changes
+        assert_eq!(soft_close.extended_end_time(300, 300, 100).unwrap(), 300);
+
+        // Inside the window the deadline is pushed back
+        assert_eq!(soft_close.extended_end_time(300, 300, 250).unwrap(), 360);
+
+        // Further extensions are capped at the hard maximum
+        assert_eq!(soft_close.extended_end_time(300, 360, 350).unwrap(), 390);
+        assert_eq!(soft_close.extended_end_time(300, 390, 385).unwrap(), 390);
+
+        // A disabled soft close never extends
+        assert_eq!(SoftCloseParams::default().extended_end_time(300, 300, 299).unwrap(), 300);
+    }
+
     #[test]
     fn test_fund_duration_validation() {
         let fund_duration: i64 = 0;